@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+/// The words a ruleset accepts, either held in memory or looked up in a file as needed.
+pub enum Dictionary {
+    Memory(HashSet<String>),
+    Disk(DiskDictionary),
+}
+
+impl Dictionary {
+    pub fn contains(&self, word: &str) -> bool {
+        match self {
+            Dictionary::Memory(words) => words.contains(word),
+            Dictionary::Disk(words) => words.contains(word),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Dictionary::Memory(words) => words.len(),
+            Dictionary::Disk(words) => words.len,
+        }
+    }
+}
+
+impl FromIterator<String> for Dictionary {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        Dictionary::Memory(iter.into_iter().collect())
+    }
+}
+
+/// A sorted file of words, one per line, searched on disk so only a line at a time is ever in
+/// memory.
+pub struct DiskDictionary {
+    // lookups only need to seek and read, but they happen behind a shared reference
+    file: RefCell<BufReader<File>>,
+    file_len: u64,
+    len: usize,
+}
+
+impl DiskDictionary {
+    /// Writes `words` to `path` in the format [`DiskDictionary::open`] expects.
+    pub fn write(path: &Path, words: impl IntoIterator<Item = String>) -> io::Result<()> {
+        let mut words = words.into_iter().collect::<Vec<_>>();
+        words.sort_unstable();
+        words.dedup();
+
+        let mut contents = words.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+
+    /// Opens a file of words which must already be sorted, one per line.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        // counting the words means reading the whole file once, but only a line at a time
+        let mut file = BufReader::new(File::open(path)?);
+        let mut len = 0;
+        let mut line = String::new();
+        while file.read_line(&mut line)? > 0 {
+            len += 1;
+            line.clear();
+        }
+        let file_len = file.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            file: RefCell::new(file),
+            file_len,
+            len,
+        })
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        // a read error can't be reported from here, and a word we can't read isn't one we know
+        self.search(word).unwrap_or(false)
+    }
+
+    fn search(&self, word: &str) -> io::Result<bool> {
+        // binary search over byte offsets: every line starting before lo sorts before the word,
+        // and no line starting at or after hi is the word
+        let (mut lo, mut hi) = (0, self.file_len);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let (start, line) = match self.line_from(mid)? {
+                Some((start, line)) if start < hi => (start, line),
+                _ => {
+                    hi = mid;
+                    continue;
+                }
+            };
+
+            match line.as_str().cmp(word) {
+                Ordering::Equal => return Ok(true),
+                Ordering::Less => lo = start + line.len() as u64 + 1,
+                Ordering::Greater => hi = mid,
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Reads the first whole line starting at or after `offset`, along with where it starts.
+    fn line_from(&self, offset: u64) -> io::Result<Option<(u64, String)>> {
+        let mut file = self.file.borrow_mut();
+
+        // unless we're at the very start, skip the rest of the line the offset lands in - looking
+        // from the byte before means a line starting exactly at the offset isn't skipped
+        let mut start = offset;
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset - 1))?;
+            let mut skipped = vec![];
+            start = offset - 1 + file.read_until(b'\n', &mut skipped)? as u64;
+        } else {
+            file.seek(SeekFrom::Start(0))?;
+        }
+
+        let mut line = String::new();
+        if file.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+
+        Ok(Some((start, line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn disk_dictionary_matches_memory() {
+        let words = [
+            "ABC", "ADG", "BEH", "CAT", "CATATE", "CFI", "DEF", "EAT", "GHI", "Z",
+        ];
+        let memory = words.iter().map(|&w| w.to_owned()).collect::<Dictionary>();
+
+        let path = env::temp_dir().join(format!("paperbark-test-{}.txt", std::process::id()));
+        DiskDictionary::write(&path, words.iter().rev().map(|&w| w.to_owned())).unwrap();
+        let disk = Dictionary::Disk(DiskDictionary::open(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(disk.len(), memory.len());
+
+        let probes = [
+            "", "A", "AB", "ABCD", "CA", "CATATES", "DEE", "EAT", "YYY", "ZZ",
+        ];
+        for word in words.into_iter().chain(probes) {
+            assert_eq!(disk.contains(word), memory.contains(word), "{word}");
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Http(reqwest::Error),
+    Parse(serde_json::Error),
+    /// One of the documents a puzzle is made from, named by `document`, isn't what was expected.
+    ParseDocument {
+        document: &'static str,
+        error: serde_json::Error,
+    },
+    Validation(String),
+    TerminalSize {
+        width: u16,
+        height: u16,
+        min_width: u16,
+        min_height: u16,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Http(e) => write!(f, "download failed: {e}"),
+            Error::Parse(e) => write!(f, "couldn't parse puzzle: {e}"),
+            Error::ParseDocument { document, error } => {
+                write!(f, "failed to parse {document} data: {error}")
+            }
+            Error::Validation(message) => write!(f, "invalid puzzle: {message}"),
+            Error::TerminalSize {
+                width,
+                height,
+                min_width,
+                min_height,
+            } => write!(
+                f,
+                "terminal is {width}x{height} but needs to be at least {min_width}x{min_height}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Http(e) => Some(e),
+            Error::Parse(e) => Some(e),
+            Error::ParseDocument { error, .. } => Some(error),
+            Error::Validation(_) | Error::TerminalSize { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e)
+    }
+}
@@ -1,5 +1,14 @@
 use itertools::iproduct;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+
+use crate::dictionary::Dictionary;
+use crate::error::Error;
+use crate::topology::{Orthogonal, Topology};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Square {
@@ -8,7 +17,7 @@ pub struct Square {
 }
 
 impl Square {
-    fn is_neighbour_of(&self, other: Square) -> bool {
+    pub fn is_neighbour_of(&self, other: Square) -> bool {
         let abs_diff = |a: usize, b: usize| a.max(b) - a.min(b);
 
         // manhattan distance
@@ -20,12 +29,89 @@ impl Square {
     }
 }
 
+/// A line to reflect a board in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// Swaps the top and bottom.
+    Horizontal,
+    /// Swaps the left and right.
+    Vertical,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Self::Horizontal),
+            "vertical" => Ok(Self::Vertical),
+            _ => Err(format!("unknown axis \"{s}\"")),
+        }
+    }
+}
+
+impl Square {
+    /// Where the square ends up when a board `width` by `height` is turned clockwise by
+    /// `quarter_turns`, to match [`Board::rotated`].
+    pub fn rotated(self, quarter_turns: u8, width: usize, height: usize) -> Square {
+        let Square { x, y } = self;
+        match quarter_turns % 4 {
+            0 => Square { x, y },
+            1 => Square {
+                x: height - 1 - y,
+                y: x,
+            },
+            2 => Square {
+                x: width - 1 - x,
+                y: height - 1 - y,
+            },
+            _ => Square {
+                x: y,
+                y: width - 1 - x,
+            },
+        }
+    }
+
+    /// Where the square ends up when a board `width` by `height` is reflected in `axis`, to match
+    /// [`Board::mirrored`].
+    pub fn mirrored(self, axis: Axis, width: usize, height: usize) -> Square {
+        let Square { x, y } = self;
+        match axis {
+            Axis::Horizontal => Square {
+                x,
+                y: height - 1 - y,
+            },
+            Axis::Vertical => Square {
+                x: width - 1 - x,
+                y,
+            },
+        }
+    }
+}
+
+impl Ord for Square {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let x_ordering = self.x.cmp(&other.x);
+        let y_ordering = self.y.cmp(&other.y);
+
+        // top-to-bottom (y first), left-to-right (x second)
+        y_ordering.then(x_ordering)
+    }
+}
+
+impl PartialOrd for Square {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl From<(usize, usize)> for Square {
     fn from(s: (usize, usize)) -> Self {
         Self { x: s.0, y: s.1 }
     }
 }
 
+#[derive(PartialEq, Eq)]
 pub struct Board {
     width: usize,
     height: usize,
@@ -33,19 +119,26 @@ pub struct Board {
 }
 
 impl Board {
-    pub fn new(width: usize, board: impl Into<String>) -> Self {
+    pub fn new(width: usize, board: impl Into<String>) -> Result<Self, Error> {
         let board = board.into();
 
-        assert_eq!(board.len() % width, 0);
-        let height = board.len() / width;
+        if width == 0 || board.is_empty() {
+            return Err(Error::Validation("the board has no squares".to_owned()));
+        }
 
-        let board = board.chars().collect();
+        // count letters rather than bytes, since not every letter fits in one
+        let board = board.chars().collect::<Vec<_>>();
+        if board.len() % width != 0 {
+            let message = format!("{} letters don't fill rows of {width}", board.len());
+            return Err(Error::Validation(message));
+        }
+        let height = board.len() / width;
 
-        Self {
+        Ok(Self {
             width,
             height,
             board,
-        }
+        })
     }
 
     pub fn width(&self) -> usize {
@@ -56,113 +149,615 @@ impl Board {
         self.height
     }
 
-    pub fn get(&self, s: Square) -> char {
+    /// Writes the board on one line as `WIDTHxHEIGHT:LETTERS`, e.g. `3x3:ABCDEFGHI`.
+    pub fn to_compact_string(&self) -> String {
+        let letters = self.board.iter().collect::<String>();
+
+        format!("{}x{}:{letters}", self.width, self.height)
+    }
+
+    /// Parses a board written by [`Board::to_compact_string`].
+    pub fn from_compact_string(s: &str) -> Result<Self, Error> {
+        let malformed = || Error::Validation(format!("{s:?} isn't a board like 3x3:ABCDEFGHI"));
+
+        let (dimensions, letters) = s.split_once(':').ok_or_else(malformed)?;
+        let (width, height) = dimensions.split_once('x').ok_or_else(malformed)?;
+        let width = width.parse::<usize>().map_err(|_| malformed())?;
+        let height = height.parse::<usize>().map_err(|_| malformed())?;
+
+        if width == 0 || letters.chars().count() != width * height {
+            return Err(malformed());
+        }
+
+        Board::new(width, letters)
+    }
+
+    /// The letter at `s`, or `None` if it's off the board.
+    pub fn get(&self, s: Square) -> Option<char> {
+        if s.x >= self.width || s.y >= self.height {
+            return None;
+        }
+
+        Some(self.letter(s))
+    }
+
+    /// The letter at `s`, which must be on the board, for the many places that already know it is.
+    pub fn letter(&self, s: Square) -> char {
         self.board[s.y * self.width + s.x]
     }
+
+    pub fn squares(&self) -> impl Iterator<Item = Square> {
+        iproduct!(0..self.height, 0..self.width).map(|(y, x)| (x, y).into())
+    }
+
+    /// The board turned clockwise by `quarter_turns`.
+    pub fn rotated(&self, quarter_turns: u8) -> Board {
+        let (width, height) = match quarter_turns % 2 {
+            0 => (self.width, self.height),
+            _ => (self.height, self.width),
+        };
+
+        self.transformed(width, height, |s| {
+            s.rotated(quarter_turns, self.width, self.height)
+        })
+    }
+
+    /// The board reflected in `axis`.
+    pub fn mirrored(&self, axis: Axis) -> Board {
+        self.transformed(self.width, self.height, |s| {
+            s.mirrored(axis, self.width, self.height)
+        })
+    }
+
+    /// Builds a `width` by `height` board by moving every square to wherever `to` says.
+    fn transformed(&self, width: usize, height: usize, to: impl Fn(Square) -> Square) -> Board {
+        let mut board = vec![' '; width * height];
+        for square in self.squares() {
+            let Square { x, y } = to(square);
+            board[y * width + x] = self.letter(square);
+        }
+
+        Board {
+            width,
+            height,
+            board,
+        }
+    }
+
+    pub fn neighbours(&self, s: Square) -> impl Iterator<Item = Square> {
+        let (width, height) = (self.width, self.height);
+        let up = s.y.checked_sub(1).map(|y| Square { x: s.x, y });
+        let down = (s.y + 1 < height).then(|| Square { x: s.x, y: s.y + 1 });
+        let left = s.x.checked_sub(1).map(|x| Square { x, y: s.y });
+        let right = (s.x + 1 < width).then(|| Square { x: s.x + 1, y: s.y });
+
+        [up, down, left, right].into_iter().flatten()
+    }
+}
+
+impl fmt::Debug for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Board({}x{}) {{", self.width, self.height)?;
+        for row in self.board.chunks(self.width) {
+            writeln!(f, "    {}", row.iter().collect::<String>())?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Union-find over a region's squares, so whether the region is in one piece is known as soon as
+/// a square is added rather than by searching the whole region.
+#[derive(Clone, Default, Debug)]
+struct Components {
+    parent: HashMap<Square, Square>,
+    // only kept up to date for the root of each component
+    size: HashMap<Square, usize>,
+    count: usize,
+}
+
+impl Components {
+    fn insert(&mut self, square: Square) {
+        self.parent.insert(square, square);
+        self.size.insert(square, 1);
+        self.count += 1;
+
+        let Square { x, y } = square;
+        let neighbours = [
+            x.checked_sub(1).map(|x| Square { x, y }),
+            y.checked_sub(1).map(|y| Square { x, y }),
+            Some(Square { x: x + 1, y }),
+            Some(Square { x, y: y + 1 }),
+        ];
+        for neighbour in neighbours.into_iter().flatten() {
+            if self.parent.contains_key(&neighbour) {
+                self.union(square, neighbour);
+            }
+        }
+    }
+
+    fn find(&mut self, mut square: Square) -> Square {
+        // path halving: point every other square on the way up at its grandparent
+        while self.parent[&square] != square {
+            let grandparent = self.parent[&self.parent[&square]];
+            self.parent.insert(square, grandparent);
+            square = grandparent;
+        }
+
+        square
+    }
+
+    fn union(&mut self, a: Square, b: Square) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        // hang the smaller tree off the larger one to keep the trees shallow
+        let (big, small) = if self.size[&a] >= self.size[&b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent.insert(small, big);
+        let small_size = self.size.remove(&small).unwrap();
+        *self.size.get_mut(&big).unwrap() += small_size;
+        self.count -= 1;
+    }
 }
 
-#[derive(Clone, PartialEq)]
-pub struct Region(HashSet<Square>);
+#[derive(Clone)]
+pub struct Region {
+    // kept in reading order, so building the region's word doesn't need a sort
+    squares: BTreeSet<Square>,
+    components: Components,
+}
 
 impl Region {
     pub fn new() -> Self {
-        Self(HashSet::new())
+        Self {
+            squares: BTreeSet::new(),
+            components: Components::default(),
+        }
+    }
+
+    /// Builds a region from a set of squares in one go.
+    pub fn from_squares(squares: impl IntoIterator<Item = Square>) -> Self {
+        let mut region = Self::new();
+        for square in squares {
+            region.add_square(square);
+        }
+
+        region
     }
 
     pub fn add_square(&mut self, square: Square) -> bool {
-        self.0.insert(square)
+        let added = self.squares.insert(square);
+        if added {
+            self.components.insert(square);
+        }
+
+        added
     }
 
     pub fn remove_square(&mut self, square: Square) -> bool {
-        self.0.remove(&square)
+        let removed = self.squares.remove(&square);
+        if removed {
+            // union-find can't split components apart, so start again from what's left
+            self.components = Components::default();
+            for &square in &self.squares {
+                self.components.insert(square);
+            }
+        }
+
+        removed
+    }
+
+    /// The number of separate pieces the region is made up of.
+    pub fn component_count(&self) -> usize {
+        self.components.count
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.component_count() <= 1
+    }
+
+    /// Whether the squares all lie in one row or one column.
+    pub fn is_straight(&self) -> bool {
+        let mut squares = self.squares();
+        let first = match squares.next() {
+            Some(first) => first,
+            None => return true,
+        };
+        let (mut same_row, mut same_column) = (true, true);
+        for square in squares {
+            same_row &= square.y == first.y;
+            same_column &= square.x == first.x;
+        }
+
+        same_row || same_column
     }
 
+    /// The region's squares, in reading order since that's how they're kept.
     pub fn squares(&self) -> impl Iterator<Item = Square> + '_ {
-        self.0.iter().copied()
+        self.squares.iter().copied()
+    }
+
+    pub fn contains(&self, square: Square) -> bool {
+        self.squares.contains(&square)
     }
 
     pub fn size(&self) -> usize {
-        self.0.len()
+        self.squares.len()
+    }
+
+    /// The same as [`Region::squares`], for callers which depend on getting the squares in
+    /// reading order to say so.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = Square> + '_ {
+        self.squares()
     }
 
+    /// The region's letters in reading order, which is all a region knows about on its own.
     pub fn word(&self, board: &Board) -> String {
-        let mut squares = self.0.iter().copied().collect::<Vec<_>>();
-        squares.sort_unstable_by(|a, b| {
-            let x_ordering = a.x.cmp(&b.x);
-            let y_ordering = a.y.cmp(&b.y);
+        self.iter_sorted().map(|s| board.letter(s)).collect()
+    }
 
-            // top-to-bottom (y first), left-to-right (x second)
-            y_ordering.then(x_ordering)
-        });
+    /// The region's letters in the order of `path`, or `None` unless `path` visits each of the
+    /// region's squares exactly once, stepping between the squares `topology` says are next to
+    /// each other.
+    pub fn word_along_path<T: Topology + ?Sized>(
+        &self,
+        topology: &T,
+        board: &Board,
+        path: &[Square],
+    ) -> Option<String> {
+        let visited = path.iter().copied().collect::<BTreeSet<_>>();
+        if visited.len() != path.len() || visited != self.squares {
+            return None;
+        }
+        let is_connected = path
+            .windows(2)
+            .all(|step| topology.is_neighbour(step[0], step[1], board));
+        if !is_connected {
+            return None;
+        }
+
+        Some(path.iter().map(|&s| board.letter(s)).collect())
+    }
+
+    /// The region's letters in alphabetical order, for when which letters matters but the order
+    /// they're read in doesn't.
+    #[allow(dead_code)]
+    pub fn chars(&self, board: &Board) -> Vec<char> {
+        let mut letters = self.squares().map(|s| board.letter(s)).collect::<Vec<_>>();
+        letters.sort_unstable();
+
+        letters
+    }
+
+    /// Every way the region's letters can be read: in reading order, column by column, and along
+    /// each path which visits every square once, in either direction, stepping between the squares
+    /// `topology` says are next to each other.
+    pub fn readings<T: Topology + ?Sized>(&self, topology: &T, board: &Board) -> BTreeSet<String> {
+        let mut readings = BTreeSet::new();
+        if self.size() == 0 {
+            return readings;
+        }
+
+        readings.insert(self.word(board));
+
+        let mut by_column = self.squares().collect::<Vec<_>>();
+        by_column.sort_by_key(|s| (s.x, s.y));
+        readings.insert(by_column.iter().map(|&s| board.letter(s)).collect());
+
+        for start in self.squares() {
+            let mut path = vec![start];
+            self.extend_path(topology, board, &mut path, &mut readings);
+        }
 
-        squares.into_iter().map(|s| board.get(s)).collect()
+        readings
+    }
+
+    fn extend_path<T: Topology + ?Sized>(
+        &self,
+        topology: &T,
+        board: &Board,
+        path: &mut Vec<Square>,
+        readings: &mut BTreeSet<String>,
+    ) {
+        if path.len() == self.size() {
+            // every step was to a neighbour, so the path always reads
+            readings.extend(self.word_along_path(topology, board, path));
+            return;
+        }
+
+        let last = *path.last().unwrap();
+        let next = topology
+            .neighbours(last, board)
+            .into_iter()
+            .filter(|&s| self.contains(s) && !path.contains(&s))
+            .collect::<Vec<_>>();
+        for square in next {
+            path.push(square);
+            self.extend_path(topology, board, path, readings);
+            path.pop();
+        }
+    }
+
+    /// The number of square edges on the region's boundary, i.e. between one of its squares and
+    /// a square which isn't in it (or the edge of the board).
+    #[allow(dead_code)]
+    pub fn perimeter(&self) -> usize {
+        // every square has four edges, less one for each neighbour also in the region
+        self.squares()
+            .map(|s| {
+                let neighbours = [
+                    s.x.checked_sub(1).map(|x| (x, s.y)),
+                    Some((s.x + 1, s.y)),
+                    s.y.checked_sub(1).map(|y| (s.x, y)),
+                    Some((s.x, s.y + 1)),
+                ];
+                let inside = neighbours
+                    .into_iter()
+                    .flatten()
+                    .filter(|&n| self.contains(n.into()))
+                    .count();
+
+                4 - inside
+            })
+            .sum()
+    }
+
+    pub fn centroid_square(&self) -> Option<Square> {
+        if self.size() == 0 {
+            return None;
+        }
+
+        let size = self.size() as f64;
+        let mean_x = self.squares().map(|s| s.x as f64).sum::<f64>() / size;
+        let mean_y = self.squares().map(|s| s.y as f64).sum::<f64>() / size;
+        let distance = |s: &Square| (s.x as f64 - mean_x).powi(2) + (s.y as f64 - mean_y).powi(2);
+
+        // the centroid itself may not be in the region (think of an L shape), so pick the closest
+        // square that is, breaking ties in reading order
+        self.squares()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)).then(a.cmp(b)))
     }
 
     fn is_in_bounds(&self, board: &Board) -> bool {
         let is_out_of_bounds = self
-            .0
+            .squares
             .iter()
             .any(|s| s.x >= board.width || s.y >= board.height);
 
         !is_out_of_bounds
     }
 
-    fn is_contiguous(&self) -> bool {
-        if self.size() == 0 {
-            return true;
-        }
+    /// Whether the region could be placed on `board` as far as its shape goes: every square is on
+    /// the board and they're all in one piece with the squares `topology` says are next to each
+    /// other. Words and other regions aren't considered.
+    pub fn is_valid_shape(&self, topology: &dyn Topology, board: &Board) -> bool {
+        self.is_in_bounds(board) && topology.is_contiguous(self, board)
+    }
 
-        let mut so_far = HashSet::new();
-        let mut remaining = self.0.clone();
+    /// Whether the region is in one piece, searching it from scratch with the squares `topology`
+    /// says are next to each other. The squares must all be on `board`.
+    pub fn is_contiguous<T: Topology + ?Sized>(&self, topology: &T, board: &Board) -> bool {
+        self.piece_count(topology, board) <= 1
+    }
 
-        let start = *remaining.iter().next().unwrap();
-        so_far.insert(start);
-        remaining.remove(&start);
+    /// The number of separate pieces the region is made up of, searching it from scratch like
+    /// [`Region::is_contiguous`].
+    pub fn piece_count<T: Topology + ?Sized>(&self, topology: &T, board: &Board) -> usize {
+        let mut visited = HashSet::new();
+        let mut pieces = 0;
+        for &start in &self.squares {
+            if !visited.insert(start) {
+                continue;
+            }
 
-        while !remaining.is_empty() {
-            // try and find a square in our remaining set which is adjacent to at least one square
-            // in the region we've built up so far
-            let adjacent = remaining
-                .iter()
-                .find(|s1| so_far.iter().any(|s2| s1.is_neighbour_of(*s2)))
-                .copied();
-
-            // if we can find one, add it to the region, if not, the region isn't contiguous
-            match adjacent {
-                Some(s) => {
-                    so_far.insert(s);
-                    remaining.remove(&s);
+            // flood out from a square no other piece reached
+            pieces += 1;
+            let mut queue = VecDeque::from([start]);
+            while let Some(square) = queue.pop_front() {
+                for neighbour in topology.neighbours(square, board) {
+                    if self.squares.contains(&neighbour) && visited.insert(neighbour) {
+                        queue.push_back(neighbour);
+                    }
                 }
-                None => return false,
             }
         }
 
-        true
+        pieces
+    }
+}
+
+// the connectivity information is derived from the squares, so only they matter
+impl PartialEq for Region {
+    fn eq(&self, other: &Self) -> bool {
+        self.squares == other.squares
+    }
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Region").field(&self.squares).finish()
+    }
+}
+
+impl FromIterator<Square> for Region {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        Self::from_squares(iter)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Scoring {
+    /// Each word scores the square of its length.
+    Length,
+    /// Each word scores the sum of its letters' values, multiplied by its length if
+    /// `length_bonus` is set.
+    Letters { length_bonus: bool },
+}
+
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "length" => Ok(Self::Length),
+            "letters" => Ok(Self::Letters {
+                length_bonus: false,
+            }),
+            "letters-bonus" => Ok(Self::Letters { length_bonus: true }),
+            _ => Err(format!("unknown scoring scheme \"{s}\"")),
+        }
+    }
+}
+
+/// What shapes regions may take.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Shape {
+    /// Any squares which join up, like the original game.
+    AnyContiguous,
+    /// A single row or column, like a word search.
+    StraightLine,
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Self::AnyContiguous),
+            "straight" => Ok(Self::StraightLine),
+            _ => Err(format!("unknown shape \"{s}\"")),
+        }
     }
 }
 
+/// The usual english scrabble tile values.
+fn default_letter_values() -> HashMap<char, u32> {
+    let values: [(&str, u32); 7] = [
+        ("AEILNORSTU", 1),
+        ("DG", 2),
+        ("BCMP", 3),
+        ("FHVWY", 4),
+        ("K", 5),
+        ("JX", 8),
+        ("QZ", 10),
+    ];
+
+    values
+        .into_iter()
+        .flat_map(|(letters, value)| letters.chars().map(move |c| (c, value)))
+        .collect()
+}
+
 pub struct Ruleset {
     pub min_length: usize,
     pub max_length: usize,
-    pub dictionary: HashSet<String>,
+    pub dictionary: Dictionary,
+    pub letter_values: HashMap<char, u32>,
+    pub scoring: Scoring,
+    pub allow_duplicate_words: bool,
+    pub topology: Box<dyn Topology>,
+    pub shape: Shape,
+}
+
+impl Ruleset {
+    /// Creates a ruleset scored by word length, with the default letter values, on a plain grid,
+    /// which allows the same word to be used more than once and regions of any shape like the
+    /// original game.
+    pub fn new(min_length: usize, max_length: usize, dictionary: Dictionary) -> Self {
+        Self {
+            min_length,
+            max_length,
+            dictionary,
+            letter_values: default_letter_values(),
+            scoring: Scoring::Length,
+            allow_duplicate_words: true,
+            topology: Box::new(Orthogonal),
+            shape: Shape::AnyContiguous,
+        }
+    }
+
+    /// Finds the words in the dictionary which the region can be read as, by any of
+    /// [`Region::readings`]. A region too short or too long to be a word can't be read as any.
+    pub fn words_for_region(&self, region: &Region, board: &Board) -> Vec<String> {
+        if !(self.min_length..=self.max_length).contains(&region.size()) {
+            return vec![];
+        }
+
+        region
+            .readings(&*self.topology, board)
+            .into_iter()
+            .filter(|word| self.dictionary.contains(word))
+            .collect()
+    }
+
+    pub fn score_word(&self, word: &str) -> usize {
+        let length = word.chars().count();
+        match self.scoring {
+            // every complete board covers the same number of squares, so reward fewer, longer words
+            Scoring::Length => length * length,
+            Scoring::Letters { length_bonus } => {
+                // letters without a value (punctuation, accented letters...) don't score
+                let sum = word
+                    .chars()
+                    .map(|c| self.letter_values.get(&c).copied().unwrap_or(0) as usize)
+                    .sum::<usize>();
+
+                if length_bonus {
+                    sum * length
+                } else {
+                    sum
+                }
+            }
+        }
+    }
 }
 
 pub struct CheckedRegion<'a>(&'a Region);
 
-#[derive(Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum CheckRegionError {
     TooShort,
     TooLong,
     OutOfBounds,
     Overlapping,
     NotContiguous,
+    NotStraight,
     NotInDictionary,
+    DuplicateWord,
+}
+
+/// How many changes [`Game::undo`] can go back through, unless set otherwise with
+/// [`Game::set_history_limit`].
+pub const HISTORY_LIMIT: usize = 256;
+
+/// A change to the regions of a game, as kept for undoing and redoing it.
+enum Change<D> {
+    Added(Region, D),
+    Removed(Region, D),
+    /// A region's squares changed from the first region to the second.
+    Replaced(Region, Region),
+    /// Every region was taken off the board at once.
+    Cleared(Vec<(Region, D)>),
 }
 
 pub struct Game<'a, D> {
     board: &'a Board,
     ruleset: &'a Ruleset,
     regions: Vec<(Region, D)>,
+    // every square covered by one of the regions, kept up to date as they change
+    occupied: HashSet<Square>,
+    // when set, only these squares are played on and the rest of the board is left alone
+    play_area: Option<Region>,
+    // changes to undo, oldest first, and those undone which can be redone, most recently undone
+    // last
+    undo_stack: VecDeque<Change<D>>,
+    redo_stack: Vec<Change<D>>,
+    history_limit: usize,
 }
 
 impl<'a, D> Game<'a, D> {
@@ -171,114 +766,841 @@ impl<'a, D> Game<'a, D> {
             board,
             ruleset,
             regions: vec![],
+            occupied: HashSet::new(),
+            play_area: None,
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            history_limit: HISTORY_LIMIT,
+        }
+    }
+
+    /// Creates a game played only on the squares of `play_area`, which must be on the board. The
+    /// other squares can't be part of any region, and don't need covering to complete the game.
+    pub fn with_play_area(board: &'a Board, ruleset: &'a Ruleset, play_area: Region) -> Self {
+        Self {
+            play_area: Some(play_area),
+            ..Self::new(board, ruleset)
         }
     }
 
+    /// Keeps at most `limit` changes for [`Game::undo`], or none at all with 0 for a game which
+    /// will never be undone, like one being searched for solutions.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        while self.undo_stack.len() > limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Forgets every change, so what's on the board now can't be undone.
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Whether `square` is part of the game, which every square is unless there's a play area.
+    pub fn is_playable(&self, square: Square) -> bool {
+        self.play_area
+            .as_ref()
+            .is_none_or(|play_area| play_area.contains(square))
+    }
+
+    /// The number of squares to be covered to complete the game.
+    pub fn playable_squares(&self) -> usize {
+        match &self.play_area {
+            Some(play_area) => play_area.size(),
+            None => self.board.width() * self.board.height(),
+        }
+    }
+
+    /// Whether every playable square of the board is covered by a region. Boards always have at
+    /// least one square, so a game with no regions is never complete.
     pub fn is_complete(&self) -> bool {
-        let all_squares = iproduct!(0..self.board.width(), 0..self.board.height())
+        iproduct!(0..self.board.width(), 0..self.board.height())
             .map(|s| s.into())
-            .collect::<HashSet<_>>();
-        let used_squares = self.regions.iter().map(|(region, _)| &region.0).fold(
-            HashSet::new(),
-            |mut used_squares, region| {
-                used_squares.extend(region.iter().copied());
-                used_squares
-            },
-        );
+            .filter(|&s| self.is_playable(s))
+            .all(|s| self.occupied.contains(&s))
+    }
 
-        all_squares.difference(&used_squares).count() == 0
+    /// Whether the board is complete and every region on it is still one
+    /// [`Game::check_region`] would accept, as any of the words [`Ruleset::words_for_region`] finds
+    /// in it. Regions are checked when they're added, so this only differs from
+    /// [`Game::is_complete`] if something has slipped through.
+    pub fn is_solved(&self) -> bool {
+        self.is_complete()
+            && self.regions.iter().enumerate().all(|(i, (region, _))| {
+                let words = self.ruleset.words_for_region(region, self.board);
+                words.iter().any(|word| {
+                    self.check_region_ignoring(region, Some(word), Some(i))
+                        .is_ok()
+                })
+            })
     }
 
     pub fn check_region<'b>(
         &self,
         region: &'b Region,
     ) -> Result<CheckedRegion<'b>, CheckRegionError> {
-        if region.size() < self.ruleset.min_length {
-            return Err(CheckRegionError::TooShort);
-        }
-
-        if region.size() > self.ruleset.max_length {
-            return Err(CheckRegionError::TooLong);
-        }
-
-        if !region.is_in_bounds(self.board) {
-            return Err(CheckRegionError::OutOfBounds);
-        }
+        self.check_region_ignoring(region, None, None)
+    }
 
-        let is_overlapping = self
-            .regions
-            .iter()
-            .flat_map(|(region, _)| region.squares())
-            .any(|square| region.0.contains(&square));
-        if is_overlapping {
-            return Err(CheckRegionError::Overlapping);
-        }
+    /// Checks a region as [`Game::check_region`] does, but looks up `word` in the dictionary
+    /// instead of the region's letters in reading order, for when the letters were read some
+    /// other way.
+    pub fn check_region_as<'b>(
+        &self,
+        region: &'b Region,
+        word: &str,
+    ) -> Result<CheckedRegion<'b>, CheckRegionError> {
+        self.check_region_ignoring(region, Some(word), None)
+    }
 
-        if !region.is_contiguous() {
-            return Err(CheckRegionError::NotContiguous);
-        }
+    /// Checks a region as [`Game::check_region`] does, apart from whether it spells a word, for a
+    /// region read in an order which has since been lost.
+    pub fn check_region_unread<'b>(
+        &self,
+        region: &'b Region,
+    ) -> Result<CheckedRegion<'b>, CheckRegionError> {
+        let mut first_error = None;
+        let _ = self.region_errors(region, None, None, &mut |error| match error {
+            CheckRegionError::NotInDictionary | CheckRegionError::DuplicateWord => {
+                ControlFlow::Continue(())
+            }
+            error => {
+                first_error = Some(error);
+                ControlFlow::Break(())
+            }
+        });
 
-        let word = region.word(self.board);
-        if !self.ruleset.dictionary.contains(&word) {
-            return Err(CheckRegionError::NotInDictionary);
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(CheckedRegion(region)),
         }
-
-        Ok(CheckedRegion(region))
     }
 
-    pub fn add_region(&mut self, region: CheckedRegion, data: D) {
-        let region = (*region.0).clone();
+    /// Checks a region as [`Game::check_region`] does, but carries on past the first problem to
+    /// report every one. A region off the board or over another region is only reported as such,
+    /// since there's no reading its letters.
+    pub fn check_region_all<'b>(
+        &self,
+        region: &'b Region,
+    ) -> Result<CheckedRegion<'b>, Vec<CheckRegionError>> {
+        self.check_region_all_ignoring(region, None)
+    }
 
-        self.regions.push((region, data));
+    /// Checks a region as [`Game::check_region_all`] does, reading it as `word` like
+    /// [`Game::check_region_as`].
+    pub fn check_region_all_as<'b>(
+        &self,
+        region: &'b Region,
+        word: &str,
+    ) -> Result<CheckedRegion<'b>, Vec<CheckRegionError>> {
+        self.check_region_all_ignoring(region, Some(word))
     }
 
-    pub fn remove_region(&mut self, square: Square) -> Option<(Region, D)> {
-        let index = self
-            .regions
-            .iter()
-            .position(|(region, _)| region.0.contains(&square));
+    fn check_region_all_ignoring<'b>(
+        &self,
+        region: &'b Region,
+        word: Option<&str>,
+    ) -> Result<CheckedRegion<'b>, Vec<CheckRegionError>> {
+        let mut errors = vec![];
+        let _ = self.region_errors(region, word, None, &mut |error| {
+            errors.push(error);
+            ControlFlow::Continue(())
+        });
 
-        match index {
-            Some(index) => Some(self.regions.swap_remove(index)),
-            None => None,
+        if errors.is_empty() {
+            Ok(CheckedRegion(region))
+        } else {
+            Err(errors)
         }
     }
 
-    pub fn regions(&self) -> impl Iterator<Item = &(Region, D)> {
-        self.regions.iter()
-    }
-
-    pub fn is_square_free(&self, square: Square) -> bool {
-        let is_square_occupied = self
-            .regions
-            .iter()
-            .flat_map(|(region, _)| region.squares())
-            .any(|other| square == other);
+    /// Checks a region against every committed region apart from the one at index `ignored`, so
+    /// that committed regions can be checked again without getting in their own way. Without a
+    /// `word`, the region is read in reading order.
+    fn check_region_ignoring<'b>(
+        &self,
+        region: &'b Region,
+        word: Option<&str>,
+        ignored: Option<usize>,
+    ) -> Result<CheckedRegion<'b>, CheckRegionError> {
+        // this is how the solvers check every placement, so it stops at the first problem rather
+        // than collecting them
+        let mut first_error = None;
+        let _ = self.region_errors(region, word, ignored, &mut |error| {
+            first_error = Some(error);
+            ControlFlow::Break(())
+        });
 
-        !is_square_occupied
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(CheckedRegion(region)),
+        }
+    }
+
+    /// Calls `report` with everything wrong with a region, stopping early if it breaks.
+    fn region_errors(
+        &self,
+        region: &Region,
+        word: Option<&str>,
+        ignored: Option<usize>,
+        report: &mut impl FnMut(CheckRegionError) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let others = || {
+            self.regions
+                .iter()
+                .enumerate()
+                .filter(move |(i, _)| Some(*i) != ignored)
+                .map(|(_, (region, _))| region)
+        };
+
+        if region.size() < self.ruleset.min_length {
+            report(CheckRegionError::TooShort)?;
+        } else if region.size() > self.ruleset.max_length {
+            report(CheckRegionError::TooLong)?;
+        }
+
+        // nothing more can be said about a region which isn't all there to be read
+        if !region.is_in_bounds(self.board) || !region.squares().all(|s| self.is_playable(s)) {
+            report(CheckRegionError::OutOfBounds)?;
+            return ControlFlow::Continue(());
+        }
+
+        let ignored_region = ignored.map(|i| &self.regions[i].0);
+        let is_overlapping = region.squares().any(|square| {
+            self.occupied.contains(&square) && !ignored_region.is_some_and(|r| r.contains(square))
+        });
+        if is_overlapping {
+            report(CheckRegionError::Overlapping)?;
+            return ControlFlow::Continue(());
+        }
+
+        if !self.ruleset.topology.is_contiguous(region, self.board) {
+            report(CheckRegionError::NotContiguous)?;
+        }
+
+        if self.ruleset.shape == Shape::StraightLine && !region.is_straight() {
+            report(CheckRegionError::NotStraight)?;
+        }
+
+        let word = match word {
+            Some(word) => Cow::Borrowed(word),
+            None => Cow::Owned(region.word(self.board)),
+        };
+        if !self.ruleset.dictionary.contains(&word) {
+            report(CheckRegionError::NotInDictionary)?;
+        }
+
+        if !self.ruleset.allow_duplicate_words {
+            let is_duplicate = others().any(|region| region.word(self.board) == word);
+            if is_duplicate {
+                report(CheckRegionError::DuplicateWord)?;
+            }
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Finds the data of the region covering `square`, if there is one.
+    pub fn region_data_mut(&mut self, square: Square) -> Option<&mut D> {
+        self.regions
+            .iter_mut()
+            .find(|(region, _)| region.squares.contains(&square))
+            .map(|(_, data)| data)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = &(Region, D)> {
+        self.regions.iter()
+    }
+
+    pub fn used_squares(&self) -> usize {
+        self.occupied.len()
+    }
+
+    /// Whether `square` is playable and not yet part of a region.
+    pub fn is_square_free(&self, square: Square) -> bool {
+        if !self.is_playable(square) {
+            return false;
+        }
+
+        !self.occupied.contains(&square)
+    }
+
+    /// The letters on every free square, in alphabetical order.
+    pub fn free_letters(&self) -> Vec<char> {
+        let mut letters = self
+            .board
+            .squares()
+            .filter(|&s| self.is_square_free(s))
+            .map(|s| self.board.letter(s))
+            .collect::<Vec<_>>();
+        letters.sort_unstable();
+
+        letters
+    }
+
+    fn free_squares(&self) -> HashSet<Square> {
+        self.board()
+            .squares()
+            .filter(|&s| self.is_square_free(s))
+            .collect()
+    }
+
+    fn neighbours(&self, square: Square) -> Vec<Square> {
+        self.ruleset.topology.neighbours(square, self.board)
+    }
+
+    /// Splits the free squares into pockets of squares connected to each other.
+    fn free_pockets(&self) -> Vec<HashSet<Square>> {
+        let mut remaining = self.free_squares();
+        let mut pockets = vec![];
+
+        while let Some(&start) = remaining.iter().next() {
+            remaining.remove(&start);
+
+            let mut pocket = HashSet::from([start]);
+            let mut to_visit = vec![start];
+            while let Some(square) = to_visit.pop() {
+                for neighbour in self.neighbours(square) {
+                    if remaining.remove(&neighbour) {
+                        pocket.insert(neighbour);
+                        to_visit.push(neighbour);
+                    }
+                }
+            }
+
+            pockets.push(pocket);
+        }
+
+        pockets
+    }
+
+    /// Whether some pocket of free squares can never be filled, because it's too small to hold a
+    /// word or there's no word to be found in it at all. Once there is one, the game can't be
+    /// completed without removing regions.
+    pub fn has_unwinnable_pockets(&self) -> bool {
+        self.free_pockets().iter().any(|pocket| {
+            pocket.len() < self.ruleset.min_length
+                || self
+                    .search_placements(pocket, |_| ControlFlow::Break(()))
+                    .is_continue()
+        })
+    }
+
+    /// Whether the free squares could still all be covered by words, by backtracking over the ways
+    /// of covering the first free square. Searching can take a very long time on a big empty
+    /// board, so after finding `budget` placements it gives up and assumes they could.
+    pub fn has_possible_completion(&self, budget: usize) -> bool {
+        let mut budget = budget;
+        self.without_data()
+            .search_completions(&mut budget, &mut |_| ControlFlow::Break(()))
+            .is_break()
+    }
+
+    /// Finds a way of covering the rest of the board with words, returning the regions already on
+    /// it followed by the new ones, or `None` if there isn't one. The same game always gives the
+    /// same answer, since squares and placements are always tried in the same order.
+    pub fn solve(&self) -> Option<Vec<Region>> {
+        // there's no running out of a budget this big
+        let mut budget = usize::MAX;
+        let result = self
+            .without_data()
+            .search_completions(&mut budget, &mut |game| {
+                let regions = game.regions().map(|(region, _)| region.clone());
+                ControlFlow::Break(regions.collect())
+            });
+
+        match result {
+            ControlFlow::Break(regions) => regions,
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// A copy of the game with the same regions on the board, but without their data or any
+    /// history, to search from.
+    fn without_data(&self) -> Game<'a, ()> {
+        let mut game = Game::new(self.board, self.ruleset);
+        game.play_area = self.play_area.clone();
+        // backtracking undoes everything anyway
+        game.set_history_limit(0);
+        for (region, _) in &self.regions {
+            game.push_region(region.clone(), ());
+        }
+
+        game
+    }
+
+    /// Finds every region of free squares which would spell a word in the dictionary.
+    pub fn placements(&self) -> Vec<Region> {
+        let mut placements = vec![];
+        let _ = self.search_placements(&self.free_squares(), |region| {
+            placements.push(region);
+            ControlFlow::<()>::Continue(())
+        });
+
+        placements
+    }
+
+    /// Finds a region of free squares which could be committed right now, the first found in
+    /// reading order of its squares, for a player who's stuck.
+    ///
+    /// Regions are grown a square at a time from each free square and never past the longest word
+    /// length, so the search stops at the first that fits. At worst, when nothing fits, it tries
+    /// every contiguous region of up to `max_length` free squares: about `F * 3^(max_length - 1)`
+    /// of them for `F` free squares, each looked up in the dictionary once.
+    pub fn hint(&self) -> Option<Region> {
+        let hint = self.search_placements(&self.free_squares(), |region| {
+            // the dictionary is checked already, but the other rules may still rule it out
+            match self.check_region(&region) {
+                Ok(_) => ControlFlow::Break(region),
+                Err(_) => ControlFlow::Continue(()),
+            }
+        });
+
+        match hint {
+            ControlFlow::Break(region) => Some(region),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Finds the longest region of free squares which would spell a word in the dictionary, the
+    /// first in reading order if there's a tie.
+    pub fn longest_placeable(&self) -> Option<Region> {
+        let mut pockets = self.free_pockets();
+        pockets.sort_unstable_by_key(|pocket| pocket.iter().min().copied());
+
+        let mut longest: Option<Region> = None;
+        for pocket in pockets {
+            // a region can't be longer than the words or spill out of its pocket, so there's no
+            // point searching pockets which can't beat what's been found already, or carrying on
+            // once a region as long as possible turns up
+            let longest_possible = pocket.len().min(self.ruleset.max_length);
+            if longest_possible <= longest.as_ref().map_or(0, Region::size) {
+                continue;
+            }
+
+            let _ = self.search_placements(&pocket, |region| {
+                if region.size() > longest.as_ref().map_or(0, Region::size) {
+                    let is_longest_possible = region.size() == longest_possible;
+                    longest = Some(region);
+                    if is_longest_possible {
+                        return ControlFlow::Break(());
+                    }
+                }
+
+                ControlFlow::Continue(())
+            });
+        }
+
+        longest
+    }
+
+    /// Finds every region of the board which would spell a word in the dictionary, whether or not
+    /// its squares are already used, in reading order of the regions' squares.
+    pub fn all_placements(&self) -> Vec<Region> {
+        let mut placements = vec![];
+        let squares = self.board.squares().filter(|&s| self.is_playable(s));
+        let _ = self.search_placements(&squares.collect(), |region| {
+            placements.push(region);
+            ControlFlow::<()>::Continue(())
+        });
+        placements.sort_unstable_by(|a, b| a.squares.cmp(&b.squares));
+
+        placements
+    }
+
+    /// Finds the regions of free squares which spell a word that can't be placed anywhere else.
+    /// These are likely to be the words the puzzle's author intended.
+    pub fn forced_placements(&self) -> Vec<Region> {
+        let mut placements_by_word = HashMap::<_, Vec<_>>::new();
+        for region in self.placements() {
+            let word = region.word(self.board);
+            placements_by_word.entry(word).or_default().push(region);
+        }
+
+        let mut forced = placements_by_word
+            .into_values()
+            .filter(|placements| placements.len() == 1)
+            .flatten()
+            .collect::<Vec<_>>();
+        forced.sort_unstable_by(|a, b| a.squares.cmp(&b.squares));
+
+        forced
+    }
+
+    /// Calls `visit` with every contiguous region made up of `squares` which is the right length,
+    /// is the right shape and spells a word in the dictionary, stopping early if `visit` breaks.
+    fn search_placements<B>(
+        &self,
+        squares: &HashSet<Square>,
+        mut visit: impl FnMut(Region) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let mut roots = squares.iter().copied().collect::<Vec<_>>();
+        roots.sort_unstable();
+
+        for root in roots {
+            self.search_placements_rooted_at(squares, root, &mut visit)?;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn search_placements_rooted_at<B>(
+        &self,
+        squares: &HashSet<Square>,
+        root: Square,
+        visit: &mut impl FnMut(Region) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let extension = self
+            .neighbours(root)
+            .into_iter()
+            .filter(|s| *s > root && squares.contains(s))
+            .collect();
+
+        self.extend_placement(squares, root, &mut vec![root], extension, visit)
+    }
+
+    fn extend_placement<B>(
+        &self,
+        squares: &HashSet<Square>,
+        root: Square,
+        subset: &mut Vec<Square>,
+        mut extension: Vec<Square>,
+        visit: &mut impl FnMut(Region) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        if subset.len() >= self.ruleset.min_length {
+            let region = Region::from_squares(subset.iter().copied());
+            if self.ruleset.dictionary.contains(&region.word(self.board)) {
+                visit(region)?;
+            }
+        }
+
+        if subset.len() >= self.ruleset.max_length {
+            return ControlFlow::Continue(());
+        }
+
+        // this is the ESU algorithm for enumerating connected subgraphs: every region is grown from
+        // its smallest square (the root), and a square only joins the extension set through the
+        // first square in the subset to neighbour it, so each region is visited exactly once
+        while let Some(square) = extension.pop() {
+            // once a region has bent it can't straighten out again, so there's no growing it
+            let is_straight =
+                subset.iter().all(|s| s.y == square.y) || subset.iter().all(|s| s.x == square.x);
+            if self.ruleset.shape == Shape::StraightLine && !is_straight {
+                continue;
+            }
+
+            let mut next_extension = extension.clone();
+            for neighbour in self.neighbours(square) {
+                let is_exclusive = neighbour > root
+                    && squares.contains(&neighbour)
+                    && !subset.contains(&neighbour)
+                    && !extension.contains(&neighbour)
+                    && !next_extension.contains(&neighbour)
+                    && !subset
+                        .iter()
+                        .any(|&s| self.ruleset.topology.is_neighbour(s, neighbour, self.board));
+
+                if is_exclusive {
+                    next_extension.push(neighbour);
+                }
+            }
+
+            subset.push(square);
+            self.extend_placement(squares, root, subset, next_extension, visit)?;
+            subset.pop();
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    pub fn score(&self) -> usize {
+        self.regions
+            .iter()
+            .map(|(region, _)| self.score_region(region))
+            .sum()
+    }
+
+    /// What the region's word would score, whether or not it's been committed.
+    pub fn score_region(&self, region: &Region) -> usize {
+        self.ruleset.score_word(&region.word(self.board))
     }
 
     pub fn board(&self) -> &Board {
         self.board
     }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        self.ruleset
+    }
+}
+
+impl<'a> Game<'a, ()> {
+    /// Calls `visit` with the game each time it's completed, by backtracking over the ways of
+    /// covering its first free square, stopping early if `visit` breaks. Every placement found
+    /// costs one from `budget`, since finding them is most of the work, and once it runs out the
+    /// search breaks with `None`.
+    pub fn search_completions<B>(
+        &mut self,
+        budget: &mut usize,
+        visit: &mut impl FnMut(&Self) -> ControlFlow<B>,
+    ) -> ControlFlow<Option<B>> {
+        // every square before the first free one is covered, so whichever region covers it must
+        // start there - this way each tiling is only found once
+        let first_free = self.board.squares().find(|&s| self.is_square_free(s));
+        let root = match first_free {
+            Some(root) => root,
+            None => {
+                return match visit(self) {
+                    ControlFlow::Break(result) => ControlFlow::Break(Some(result)),
+                    ControlFlow::Continue(()) => ControlFlow::Continue(()),
+                }
+            }
+        };
+        if *budget == 0 {
+            return ControlFlow::Break(None);
+        }
+
+        let mut placements = vec![];
+        let found = self.search_placements_rooted_at(&self.free_squares(), root, &mut |region| {
+            if *budget == 0 {
+                return ControlFlow::Break(());
+            }
+            *budget -= 1;
+            placements.push(region);
+            ControlFlow::Continue(())
+        });
+        if found.is_break() {
+            return ControlFlow::Break(None);
+        }
+
+        for region in placements {
+            // the placement search only knows about the dictionary and shapes, so let the game
+            // enforce the rest of the rules
+            let checked_region = match self.check_region(&region) {
+                Ok(checked_region) => checked_region,
+                Err(_) => continue,
+            };
+            self.add_region(checked_region, ());
+
+            let result = self.search_completions(budget, visit);
+            self.remove_region(root);
+            result?;
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Changing the regions keeps a copy of what changed, so it can be undone.
+impl<'a, D: Clone> Game<'a, D> {
+    pub fn add_region(&mut self, region: CheckedRegion, data: D) {
+        let region = (*region.0).clone();
+
+        self.record(|| Change::Added(region.clone(), data.clone()));
+        self.push_region(region, data);
+    }
+
+    pub fn remove_region(&mut self, square: Square) -> Option<(Region, D)> {
+        let index = self
+            .regions
+            .iter()
+            .position(|(region, _)| region.squares.contains(&square));
+
+        let (region, data) = self.swap_remove_region(index?);
+        self.record(|| Change::Removed(region.clone(), data.clone()));
+
+        Some((region, data))
+    }
+
+    /// Takes every region off the board, returning them in the order they were added. It's
+    /// recorded as one change, so [`Game::undo`] puts them all back at once.
+    pub fn clear(&mut self) -> Vec<(Region, D)> {
+        let regions = std::mem::take(&mut self.regions);
+        self.occupied.clear();
+        if !regions.is_empty() {
+            self.record(|| Change::Cleared(regions.clone()));
+        }
+
+        regions
+    }
+
+    /// Swaps the squares of the region covering `square` for those of `region`, keeping its data,
+    /// as long as `region` passes [`Game::check_region`] once the old squares are out of the way.
+    /// Returns `None` if there's no region covering `square`.
+    pub fn replace_region(
+        &mut self,
+        square: Square,
+        region: &Region,
+    ) -> Option<Result<(), CheckRegionError>> {
+        let index = self
+            .regions
+            .iter()
+            .position(|(region, _)| region.squares.contains(&square))?;
+
+        // keep the region's place in the list, so replacing it doesn't reorder anything
+        let (old, data) = self.regions.remove(index);
+        self.vacate(&old);
+        let check = self.check_region(region).map(|_| ());
+        let new = if check.is_ok() {
+            let new = region.clone();
+            self.record(|| Change::Replaced(old, new.clone()));
+            new
+        } else {
+            old
+        };
+        self.occupy(&new);
+        self.regions.insert(index, (new, data));
+
+        Some(check)
+    }
+
+    /// Keeps `change` (made only if there's room to keep it) for undoing, which means anything
+    /// undone before it can no longer be redone.
+    fn record(&mut self, change: impl FnOnce() -> Change<D>) {
+        self.redo_stack.clear();
+        if self.history_limit == 0 {
+            return;
+        }
+
+        if self.undo_stack.len() == self.history_limit {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(change());
+    }
+
+    /// Reverses the last change to the regions, returning the regions it was made to and their
+    /// data.
+    pub fn undo(&mut self) -> Option<Vec<(Region, D)>> {
+        let change = self.undo_stack.pop_back()?;
+        let (change, undone) = self.apply_reverse(change);
+        self.redo_stack.push(change);
+
+        Some(undone)
+    }
+
+    /// Makes the last undone change again, returning the regions it was made to and their data.
+    pub fn redo(&mut self) -> Option<Vec<(Region, D)>> {
+        let change = self.redo_stack.pop()?;
+        let (change, redone) = self.apply(change);
+        self.undo_stack.push_back(change);
+
+        Some(redone)
+    }
+
+    /// Makes `change` without recording it, returning it again for the other stack.
+    fn apply(&mut self, change: Change<D>) -> (Change<D>, Vec<(Region, D)>) {
+        match change {
+            Change::Added(region, data) => {
+                self.push_region(region.clone(), data.clone());
+                (
+                    Change::Added(region.clone(), data.clone()),
+                    vec![(region, data)],
+                )
+            }
+            Change::Removed(region, _) => {
+                let (region, data) = self.take_region(&region);
+                (
+                    Change::Removed(region.clone(), data.clone()),
+                    vec![(region, data)],
+                )
+            }
+            Change::Replaced(old, new) => {
+                let data = self.swap_squares(&old, new.clone());
+                (Change::Replaced(old, new.clone()), vec![(new, data)])
+            }
+            Change::Cleared(_) => {
+                // nothing can have changed since the clear was undone, so it's everything again
+                let regions = std::mem::take(&mut self.regions);
+                self.occupied.clear();
+                (Change::Cleared(regions.clone()), regions)
+            }
+        }
+    }
+
+    /// Reverses `change` without recording it, returning it again for the other stack.
+    fn apply_reverse(&mut self, change: Change<D>) -> (Change<D>, Vec<(Region, D)>) {
+        match change {
+            Change::Added(region, _) => {
+                // the data may have changed since, like a region being recolored, so keep what it
+                // is now
+                let (region, data) = self.take_region(&region);
+                (
+                    Change::Added(region.clone(), data.clone()),
+                    vec![(region, data)],
+                )
+            }
+            Change::Removed(region, data) => {
+                self.push_region(region.clone(), data.clone());
+                (
+                    Change::Removed(region.clone(), data.clone()),
+                    vec![(region, data)],
+                )
+            }
+            Change::Replaced(old, new) => {
+                let data = self.swap_squares(&new, old.clone());
+                (Change::Replaced(old.clone(), new), vec![(old, data)])
+            }
+            Change::Cleared(regions) => {
+                for (region, data) in &regions {
+                    self.push_region(region.clone(), data.clone());
+                }
+                (Change::Cleared(regions.clone()), regions)
+            }
+        }
+    }
+
+    /// Takes `region` off the board. The history only ever refers to regions it put there, so it's
+    /// always found.
+    fn take_region(&mut self, region: &Region) -> (Region, D) {
+        let index = self.regions.iter().position(|(r, _)| r == region).unwrap();
+        self.swap_remove_region(index)
+    }
+
+    /// Swaps the squares of `from` for those of `to`, where it is in the list, and returns its data.
+    fn swap_squares(&mut self, from: &Region, to: Region) -> D {
+        self.vacate(from);
+        self.occupy(&to);
+        let (region, data) = self.regions.iter_mut().find(|(r, _)| r == from).unwrap();
+        *region = to;
+
+        data.clone()
+    }
+
+    fn push_region(&mut self, region: Region, data: D) {
+        self.occupy(&region);
+        self.regions.push((region, data));
+    }
+
+    fn swap_remove_region(&mut self, index: usize) -> (Region, D) {
+        let (region, data) = self.regions.swap_remove(index);
+        self.vacate(&region);
+
+        (region, data)
+    }
+
+    fn occupy(&mut self, region: &Region) {
+        self.occupied.extend(region.squares());
+    }
+
+    fn vacate(&mut self, region: &Region) {
+        for square in region.squares() {
+            self.occupied.remove(&square);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::topology::Toroidal;
+    use std::hint::black_box;
+    use std::time::Instant;
 
     macro_rules! region {
-        () => {
-            Region(HashSet::new())
-        };
-        ($($square:expr),+ $(,)?) => {
-            {
-                let mut squares = HashSet::new();
-                $(
-                    squares.insert($square.into());
-                )*
-                Region(squares)
-            }
+        ($($square:expr),* $(,)?) => {
+            Region::from_squares([$(Square::from($square)),*])
         };
     }
 
@@ -291,11 +1613,162 @@ mod tests {
                 "DEF",
                 "GHI",
             )
-        );
+        )
+        .unwrap();
 
         board
     }
 
+    fn ruleset() -> Ruleset {
+        let dictionary = ["AB", "ABC", "DEF", "GHI", "ADG"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+
+        Ruleset::new(2, 3, dictionary)
+    }
+
+    #[test]
+    fn board_eq() {
+        assert_eq!(board(), board());
+        assert_ne!(board(), Board::new(3, "ABCDEFGHJ").unwrap());
+        assert_ne!(
+            Board::new(2, "ABCD").unwrap(),
+            Board::new(4, "ABCD").unwrap()
+        );
+    }
+
+    #[test]
+    fn board_get() {
+        let board = board();
+        assert_eq!(board.get((0, 0).into()), Some('A'));
+        assert_eq!(board.get((2, 2).into()), Some('I'));
+
+        // just off each edge, and off both at once past the far corner
+        assert_eq!(board.get((3, 1).into()), None);
+        assert_eq!(board.get((1, 3).into()), None);
+        assert_eq!(board.get((3, 0).into()), None);
+        assert_eq!(board.get((3, 3).into()), None);
+        // squares can't go negative, so the near edges are only reachable by wrapping around
+        assert_eq!(board.get((usize::MAX, 0).into()), None);
+        assert_eq!(board.get((0, usize::MAX).into()), None);
+    }
+
+    #[test]
+    fn board_multi_byte_letters() {
+        let board = Board::new(2, "ÉTÉS").unwrap();
+        assert_eq!((board.width(), board.height()), (2, 2));
+        assert_eq!(board.get((0, 0).into()), Some('É'));
+        assert_eq!(board.get((1, 1).into()), Some('S'));
+        assert_eq!(board.get((0, 2).into()), None);
+        assert_eq!(board.to_compact_string(), "2x2:ÉTÉS");
+
+        // three letters never fill rows of two, however many bytes they take
+        assert!(Board::new(2, "ÉTÉ").is_err());
+    }
+
+    #[test]
+    fn board_rotated() {
+        let board = Board::new(3, "ABCDEF").unwrap();
+        assert_eq!(board.rotated(1).to_compact_string(), "2x3:DAEBFC");
+        assert_eq!(board.rotated(2).to_compact_string(), "3x2:FEDCBA");
+        assert_eq!(board.rotated(3).to_compact_string(), "2x3:CFBEAD");
+
+        // four quarter turns go all the way round, whichever way they're made up
+        assert_eq!(board.rotated(4), board);
+        assert_eq!(board.rotated(1).rotated(3), board);
+        assert_eq!(board.rotated(2).rotated(2), board);
+
+        // squares move with their letters
+        for quarter_turns in 0..4 {
+            let rotated = board.rotated(quarter_turns);
+            for square in board.squares() {
+                let to = square.rotated(quarter_turns, board.width(), board.height());
+                assert_eq!(rotated.get(to), board.get(square));
+            }
+        }
+    }
+
+    #[test]
+    fn board_mirrored() {
+        let board = Board::new(3, "ABCDEF").unwrap();
+        assert_eq!(
+            board.mirrored(Axis::Vertical).to_compact_string(),
+            "3x2:CBAFED"
+        );
+        assert_eq!(
+            board.mirrored(Axis::Horizontal).to_compact_string(),
+            "3x2:DEFABC"
+        );
+
+        for axis in [Axis::Horizontal, Axis::Vertical] {
+            let mirrored = board.mirrored(axis);
+            assert_eq!(mirrored.mirrored(axis), board);
+            for square in board.squares() {
+                let to = square.mirrored(axis, board.width(), board.height());
+                assert_eq!(mirrored.get(to), board.get(square));
+            }
+        }
+
+        // flipping both ways is half a turn
+        let flipped = board.mirrored(Axis::Horizontal).mirrored(Axis::Vertical);
+        assert_eq!(flipped, board.rotated(2));
+    }
+
+    #[test]
+    fn board_compact_string() {
+        assert_eq!(board().to_compact_string(), "3x3:ABCDEFGHI");
+        assert_eq!(
+            Board::from_compact_string(&board().to_compact_string()).unwrap(),
+            board()
+        );
+
+        for malformed in [
+            "",
+            "ABCDEFGHI",
+            "3:ABCDEFGHI",
+            "3x:ABC",
+            "0x0:",
+            "3x3:ABCDEFGH",
+        ] {
+            assert!(matches!(
+                Board::from_compact_string(malformed),
+                Err(Error::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn board_new_ragged() {
+        assert!(matches!(Board::new(2, "ABC"), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn board_new_empty() {
+        assert!(matches!(Board::new(0, ""), Err(Error::Validation(_))));
+        assert!(matches!(Board::new(3, ""), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn board_debug() {
+        assert_eq!(
+            format!("{:?}", board()),
+            "Board(3x3) {\n    ABC\n    DEF\n    GHI\n}"
+        );
+    }
+
+    #[test]
+    fn region_from_squares() {
+        let squares = [(0, 0), (1, 0), (1, 1)].map(Square::from);
+
+        let region = Region::from_squares(squares);
+        assert_eq!(region.size(), 3);
+        assert!(squares.into_iter().eq(region.iter_sorted()));
+
+        let collected = squares.into_iter().collect::<Region>();
+        assert_eq!(collected, region);
+    }
+
     #[test]
     fn region_size() {
         let region = region![];
@@ -311,6 +1784,20 @@ mod tests {
         assert_eq!(region.size(), 3);
     }
 
+    #[test]
+    fn region_iter_sorted() {
+        let region = region![];
+        assert_eq!(region.iter_sorted().count(), 0);
+
+        let region = region![(2, 1), (0, 1), (1, 0), (1, 1), (1, 2)];
+        let squares = region.iter_sorted().collect::<Vec<_>>();
+        let expected = [(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]
+            .into_iter()
+            .map(Square::from)
+            .collect::<Vec<_>>();
+        assert_eq!(squares, expected);
+    }
+
     #[test]
     fn region_word() {
         let board = board();
@@ -334,6 +1821,152 @@ mod tests {
         assert_eq!(region.word(&board), "ABCDFGI");
     }
 
+    #[test]
+    fn region_word_along_path() {
+        let board = board();
+        let corner = region![(0, 0), (1, 0), (1, 1)];
+        assert_eq!(corner.word(&board), "ABE");
+
+        // right then down, or back up and left, over the same squares
+        let path = [(0, 0).into(), (1, 0).into(), (1, 1).into()];
+        assert_eq!(
+            corner
+                .word_along_path(&Orthogonal, &board, &path)
+                .as_deref(),
+            Some("ABE")
+        );
+        let path = [(1, 1).into(), (1, 0).into(), (0, 0).into()];
+        assert_eq!(
+            corner
+                .word_along_path(&Orthogonal, &board, &path)
+                .as_deref(),
+            Some("EBA")
+        );
+
+        // going round a square reads nothing like reading order does
+        let square = region![(0, 0), (1, 0), (0, 1), (1, 1)];
+        let path = [(0, 0).into(), (1, 0).into(), (1, 1).into(), (0, 1).into()];
+        assert_eq!(square.word(&board), "ABDE");
+        assert_eq!(
+            square
+                .word_along_path(&Orthogonal, &board, &path)
+                .as_deref(),
+            Some("ABED")
+        );
+        // and reading order itself jumps from the end of one row to the start of the next
+        let path = square.squares().collect::<Vec<_>>();
+        assert_eq!(square.word_along_path(&Orthogonal, &board, &path), None);
+
+        // on a wrapped board, the step from one edge to the other is just another step
+        let row = region![(0, 0), (1, 0), (2, 0)];
+        let path = [(1, 0).into(), (0, 0).into(), (2, 0).into()];
+        assert_eq!(row.word_along_path(&Orthogonal, &board, &path), None);
+        assert_eq!(
+            row.word_along_path(&Toroidal, &board, &path).as_deref(),
+            Some("BAC")
+        );
+
+        // paths which miss a square, stray outside, go back over one or jump between squares which
+        // aren't next to each other don't read
+        for path in [
+            &[(0, 0).into(), (1, 0).into()][..],
+            &[(0, 0).into(), (1, 0).into(), (2, 0).into()],
+            &[(0, 0).into(), (1, 0).into(), (1, 1).into(), (1, 0).into()],
+            &[(0, 0).into(), (1, 1).into(), (1, 0).into()],
+        ] {
+            assert_eq!(corner.word_along_path(&Orthogonal, &board, path), None);
+        }
+    }
+
+    #[test]
+    fn region_chars() {
+        let board = Board::new(3, "CABBAC").unwrap();
+        assert!(region![].chars(&board).is_empty());
+
+        // letters come out sorted, repeats and all, however they're read
+        let region = region![(0, 0), (1, 0), (0, 1), (1, 1)];
+        assert_eq!(region.chars(&board), ['A', 'A', 'B', 'C']);
+        for region in [region, region![(2, 0), (2, 1)], board.squares().collect()] {
+            let mut letters = region.word(&board).chars().collect::<Vec<_>>();
+            letters.sort_unstable();
+            assert_eq!(region.chars(&board), letters);
+        }
+    }
+
+    #[test]
+    fn region_perimeter() {
+        let region =
+            |squares: &[(usize, usize)]| Region::from_squares(squares.iter().map(|&s| s.into()));
+
+        assert_eq!(Region::new().perimeter(), 0);
+        assert_eq!(region(&[(0, 0)]).perimeter(), 4);
+        assert_eq!(region(&[(3, 2)]).perimeter(), 4);
+        assert_eq!(region(&[(0, 0), (1, 0)]).perimeter(), 6);
+        assert_eq!(region(&[(1, 1), (2, 1), (1, 2), (2, 2)]).perimeter(), 8);
+        // an L has the same perimeter as the rectangle around it
+        assert_eq!(region(&[(0, 0), (0, 1), (0, 2), (1, 2)]).perimeter(), 10);
+        // pieces which don't touch just add up
+        assert_eq!(region(&[(0, 0), (2, 0)]).perimeter(), 8);
+    }
+
+    #[test]
+    fn region_centroid_square() {
+        let region = region![];
+        assert_eq!(region.centroid_square(), None);
+
+        let region = region![(1, 2)];
+        assert_eq!(region.centroid_square(), Some((1, 2).into()));
+
+        let region = region![(0, 0), (1, 0), (2, 0)];
+        assert_eq!(region.centroid_square(), Some((1, 0).into()));
+
+        let region = region![(0, 0), (1, 0)];
+        assert_eq!(region.centroid_square(), Some((0, 0).into()));
+
+        let region = region![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)];
+        assert_eq!(region.centroid_square(), Some((0, 1).into()));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_region_word() {
+        // cargo test --release -- --ignored --nocapture bench_region_word
+        const WIDTH: usize = 64;
+        const ITERATIONS: u32 = 1000;
+
+        let board = Board::new(WIDTH, "A".repeat(WIDTH * WIDTH)).unwrap();
+        let mut region = Region::new();
+        for (x, y) in iproduct!(0..WIDTH, 0..WIDTH) {
+            region.add_square((x, y).into());
+        }
+
+        // what word used to do: copy the squares out of an unordered set and sort them
+        let unordered = region.squares().collect::<HashSet<_>>();
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            let mut squares = unordered.iter().copied().collect::<Vec<_>>();
+            squares.sort_unstable();
+            black_box(
+                squares
+                    .into_iter()
+                    .map(|s| board.letter(s))
+                    .collect::<String>(),
+            );
+        }
+        let before = start.elapsed() / ITERATIONS;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(region.word(&board));
+        }
+        let after = start.elapsed() / ITERATIONS;
+
+        println!(
+            "word() on {} squares: {before:?} -> {after:?}",
+            region.size()
+        );
+    }
+
     #[test]
     fn region_is_in_bounds() {
         let board = board();
@@ -357,35 +1990,773 @@ mod tests {
         assert_eq!(region.is_in_bounds(&board), false);
     }
 
+    #[test]
+    fn region_is_valid_shape() {
+        let board = board();
+
+        assert!(region![(0, 0)].is_valid_shape(&Orthogonal, &board));
+        assert!(region![(0, 0), (1, 0), (1, 1)].is_valid_shape(&Orthogonal, &board));
+        assert!(region![(2, 0), (2, 1), (2, 2)].is_valid_shape(&Orthogonal, &board));
+
+        // in pieces
+        assert!(!region![(0, 0), (2, 0)].is_valid_shape(&Orthogonal, &board));
+        assert!(!region![(0, 0), (1, 1)].is_valid_shape(&Orthogonal, &board));
+
+        // only in one piece by wrapping around the sides
+        assert!(region![(0, 0), (2, 0)].is_valid_shape(&Toroidal, &board));
+        assert!(!region![(0, 0), (1, 1)].is_valid_shape(&Toroidal, &board));
+
+        // off the board, even though it's in one piece
+        assert!(!region![(2, 0), (3, 0)].is_valid_shape(&Orthogonal, &board));
+        assert!(!region![(0, 2), (0, 3)].is_valid_shape(&Orthogonal, &board));
+    }
+
     #[test]
     fn region_is_contiguous() {
         let board = board();
 
         let region = region![];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0)];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0), (0, 1)];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0), (1, 0)];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0), (1, 1)];
-        assert_eq!(region.is_contiguous(), false);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), false);
 
         let region = region![(0, 0), (0, 1), (1, 1)];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0), (1, 0), (1, 1)];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
 
         let region = region![(0, 0), (0, 1), (2, 2)];
-        assert_eq!(region.is_contiguous(), false);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), false);
 
         let region = region![(0, 0), (0, 1), (0, 2), (1, 0), (2, 0), (2, 1), (2, 2),];
-        assert_eq!(region.is_contiguous(), true);
+        assert_eq!(region.is_contiguous(&Orthogonal, &board), true);
+    }
+
+    #[test]
+    fn region_is_contiguous_snake() {
+        // every other row, joined at alternating ends, winding down a big board
+        let size = 40;
+        let board = Board::new(size, "A".repeat(size * size)).unwrap();
+        let snake = (0..size).flat_map(|y| match y % 4 {
+            0 | 2 => (0..size).map(|x| (x, y)).collect::<Vec<_>>(),
+            1 => vec![(size - 1, y)],
+            _ => vec![(0, y)],
+        });
+        let snake = snake.map(|s| s.into()).collect::<Vec<Square>>();
+
+        let region = Region::from_squares(snake.iter().copied());
+        assert!(region.is_contiguous(&Orthogonal, &board));
+
+        // cutting it anywhere leaves two pieces
+        for cut in [1, snake.len() / 2, snake.len() - 2] {
+            let squares = snake.iter().enumerate().filter(|&(i, _)| i != cut);
+            let region = Region::from_squares(squares.map(|(_, &s)| s));
+            assert!(!region.is_contiguous(&Orthogonal, &board));
+        }
+    }
+
+    #[test]
+    fn region_is_connected() {
+        let board = board();
+        let mut region = region![];
+        assert!(region.is_connected());
+
+        // add and remove squares in an order which merges and splits components, checking the
+        // incremental state against a full search each time
+        let steps = [
+            (true, (0, 0)),
+            (true, (2, 0)),
+            (true, (1, 1)),
+            (true, (1, 0)),
+            (true, (2, 2)),
+            (true, (2, 1)),
+            (false, (2, 1)),
+            (false, (1, 0)),
+            (true, (0, 1)),
+            (false, (1, 1)),
+        ];
+        for (add, square) in steps {
+            if add {
+                region.add_square(square.into());
+            } else {
+                region.remove_square(square.into());
+            }
+
+            assert_eq!(
+                region.is_connected(),
+                region.is_contiguous(&Orthogonal, &board)
+            );
+        }
+
+        assert_eq!(region.component_count(), 3);
+    }
+
+    #[test]
+    fn ruleset_score_word() {
+        let mut ruleset = ruleset();
+        assert_eq!(ruleset.score_word("QUIZ"), 16);
+
+        ruleset.scoring = Scoring::Letters {
+            length_bonus: false,
+        };
+        assert_eq!(ruleset.score_word("QUIZ"), 22);
+        assert_eq!(ruleset.score_word("Q-Z"), 20);
+
+        ruleset.scoring = Scoring::Letters { length_bonus: true };
+        assert_eq!(ruleset.score_word("QUIZ"), 88);
+    }
+
+    #[test]
+    fn ruleset_words_for_region() {
+        #[rustfmt::skip]
+        let board = Board::new(
+            3,
+            concat!(
+                "XOP",
+                "TSX",
+            )
+        )
+        .unwrap();
+        let dictionary = ["OPTS", "POST", "STOP", "TOSP"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 4, dictionary);
+
+        // reads OPTS in reading order, TOSP column by column, and POST along the S from its top end
+        let s_shape = region![(1, 0), (2, 0), (0, 1), (1, 1)];
+        assert_eq!(
+            ruleset.words_for_region(&s_shape, &board),
+            ["OPTS", "POST", "TOSP"]
+        );
+
+        assert!(ruleset
+            .words_for_region(&region![(1, 0), (2, 0)], &board)
+            .is_empty());
+    }
+
+    #[test]
+    fn ruleset_words_for_region_wrapping() {
+        let board = Board::new(3, "AXC").unwrap();
+        let dictionary = ["CA"].into_iter().map(|w| w.to_owned()).collect();
+        let mut ruleset = Ruleset::new(2, 2, dictionary);
+        let ends = region![(0, 0), (2, 0)];
+        assert!(ruleset.words_for_region(&ends, &board).is_empty());
+
+        // the ends of the row are next to each other around the side, so C can lead on to A
+        ruleset.topology = Box::new(Toroidal);
+        assert_eq!(ruleset.words_for_region(&ends, &board), ["CA"]);
+    }
+
+    #[test]
+    fn game_check_region_all() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        assert!(game
+            .check_region_all(&region![(0, 0), (1, 0), (2, 0)])
+            .is_ok());
+
+        // the single error check stops at the first problem, but this finds them all
+        let region = region![(0, 0), (2, 0)];
+        assert_eq!(
+            game.check_region(&region).err(),
+            Some(CheckRegionError::NotContiguous)
+        );
+        assert_eq!(
+            game.check_region_all(&region).err(),
+            Some(vec![
+                CheckRegionError::NotContiguous,
+                CheckRegionError::NotInDictionary
+            ])
+        );
+        let region = region![(0, 1)];
+        assert_eq!(
+            game.check_region_all(&region).err(),
+            Some(vec![
+                CheckRegionError::TooShort,
+                CheckRegionError::NotInDictionary
+            ])
+        );
+
+        // there are no letters to read off the board or under another region
+        let region = region![(0, 3)];
+        assert_eq!(
+            game.check_region(&region).err(),
+            Some(CheckRegionError::TooShort)
+        );
+        assert_eq!(
+            game.check_region_all(&region).err(),
+            Some(vec![
+                CheckRegionError::TooShort,
+                CheckRegionError::OutOfBounds
+            ])
+        );
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            (),
+        );
+        assert_eq!(
+            game.check_region_all(&region![(0, 0), (0, 1), (0, 2)])
+                .err(),
+            Some(vec![CheckRegionError::Overlapping])
+        );
+    }
+
+    #[test]
+    fn game_check_region_straight_line() {
+        let board = board();
+        let mut ruleset = ruleset();
+        ruleset.shape = Shape::StraightLine;
+        let game = Game::<()>::new(&board, &ruleset);
+
+        assert!(game.check_region(&region![(0, 0), (1, 0), (2, 0)]).is_ok());
+        assert!(game.check_region(&region![(0, 0), (0, 1), (0, 2)]).is_ok());
+        assert!(matches!(
+            game.check_region(&region![(0, 0), (1, 0), (1, 1)]),
+            Err(CheckRegionError::NotStraight)
+        ));
+        // the shape is only checked once the region is known to be in one piece
+        assert!(matches!(
+            game.check_region(&region![(0, 0), (2, 0)]),
+            Err(CheckRegionError::NotContiguous)
+        ));
+
+        assert!(region![(1, 2)].is_straight());
+        assert!(!region![(0, 0), (1, 1)].is_straight());
+    }
+
+    #[test]
+    fn game_play_area() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::with_play_area(&board, &ruleset, region![(0, 0), (1, 0), (2, 0)]);
+
+        assert!(game.is_playable((2, 0).into()));
+        assert!(!game.is_playable((0, 1).into()));
+        assert!(!game.is_square_free((0, 1).into()));
+        assert_eq!(game.playable_squares(), 3);
+        assert_eq!(game.free_letters(), ['A', 'B', 'C']);
+        assert!(matches!(
+            game.check_region(&region![(0, 1), (1, 1), (2, 1)]),
+            Err(CheckRegionError::OutOfBounds)
+        ));
+        assert!(game
+            .all_placements()
+            .iter()
+            .all(|region| region.squares().all(|s| s.y == 0)));
+
+        // covering the area is enough to finish
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            (),
+        );
+        assert!(game.is_complete());
+        assert!(game.is_solved());
+    }
+
+    #[test]
+    fn game_undo_redo() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        let (abc, def) = (
+            region![(0, 0), (1, 0), (2, 0)],
+            region![(0, 1), (1, 1), (2, 1)],
+        );
+        assert!(game.undo().is_none());
+
+        game.add_region(game.check_region(&abc).unwrap(), 1);
+        game.add_region(game.check_region(&def).unwrap(), 2);
+        game.remove_region((0, 0).into());
+        assert_eq!(game.regions().count(), 1);
+
+        // back through the removal and the second add
+        assert_eq!(game.undo(), Some(vec![(abc.clone(), 1)]));
+        assert_eq!(game.regions().count(), 2);
+        assert_eq!(game.undo(), Some(vec![(def.clone(), 2)]));
+        assert_eq!(game.regions().count(), 1);
+
+        // and forward again
+        assert_eq!(game.redo(), Some(vec![(def.clone(), 2)]));
+        assert!(!game.is_square_free((0, 1).into()));
+
+        // something new means what's been undone can't be redone
+        game.remove_region((0, 1).into());
+        assert!(game.redo().is_none());
+        assert_eq!(game.undo(), Some(vec![(def, 2)]));
+
+        // replacing a region's squares can be undone too
+        let ab = region![(0, 0), (1, 0)];
+        assert_eq!(game.replace_region((0, 0).into(), &ab), Some(Ok(())));
+        assert_eq!(game.undo(), Some(vec![(abc.clone(), 1)]));
+        assert!(game.regions().any(|(region, _)| region == &abc));
+        assert_eq!(game.redo(), Some(vec![(ab, 1)]));
+    }
+
+    #[test]
+    fn game_occupied_squares() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        let consistent = |game: &Game<()>| {
+            let squares = game.regions().flat_map(|(region, _)| region.squares());
+            game.occupied == squares.collect::<HashSet<_>>()
+        };
+
+        let abc = region![(0, 0), (1, 0), (2, 0)];
+        let def = region![(0, 1), (1, 1), (2, 1)];
+        game.add_region(game.check_region(&abc).unwrap(), ());
+        game.add_region(game.check_region(&def).unwrap(), ());
+        assert!(consistent(&game));
+
+        game.remove_region((1, 0).into());
+        assert!(consistent(&game));
+        assert!(game.is_square_free((1, 0).into()));
+        assert!(!game.is_square_free((1, 1).into()));
+        assert_eq!(game.used_squares(), 3);
+
+        let ab = region![(0, 0), (1, 0)];
+        game.undo();
+        assert_eq!(game.replace_region((0, 0).into(), &ab), Some(Ok(())));
+        assert!(consistent(&game));
+        assert!(game.is_square_free((2, 0).into()));
+        game.undo();
+        assert!(consistent(&game));
+        assert!(!game.is_square_free((2, 0).into()));
+    }
+
+    #[test]
+    fn game_clear() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        let abc = region![(0, 0), (1, 0), (2, 0)];
+        let def = region![(0, 1), (1, 1), (2, 1)];
+        game.add_region(game.check_region(&abc).unwrap(), 1);
+        game.add_region(game.check_region(&def).unwrap(), 2);
+
+        assert_eq!(game.clear(), [(abc.clone(), 1), (def.clone(), 2)]);
+        assert_eq!(game.regions().count(), 0);
+        assert_eq!(game.used_squares(), 0);
+        assert!(game.is_square_free((1, 1).into()));
+        assert!(game.clear().is_empty());
+
+        // the regions all come back at once, and go again the same way
+        let both = vec![(abc, 1), (def, 2)];
+        assert_eq!(game.undo(), Some(both.clone()));
+        assert_eq!(game.regions().count(), 2);
+        assert!(!game.is_square_free((1, 0).into()));
+        assert_eq!(game.redo(), Some(both.clone()));
+        assert_eq!(game.used_squares(), 0);
+        assert_eq!(game.undo(), Some(both));
+        assert!(game.undo().is_some());
+        assert_eq!(game.regions().count(), 1);
+    }
+
+    #[test]
+    fn game_history_limit() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        let abc = region![(0, 0), (1, 0), (2, 0)];
+        for _ in 0..HISTORY_LIMIT + 10 {
+            game.add_region(game.check_region(&abc).unwrap(), ());
+            game.remove_region((0, 0).into());
+        }
+
+        let mut undone = 0;
+        while game.undo().is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LIMIT);
+
+        game.set_history_limit(0);
+        game.add_region(game.check_region(&abc).unwrap(), ());
+        assert!(game.undo().is_none());
+    }
+
+    #[test]
+    fn game_check_region_duplicate_word() {
+        let board = Board::new(2, "ABAB").unwrap();
+        let mut ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+
+        game.add_region(game.check_region(&region![(0, 0), (1, 0)]).unwrap(), ());
+        assert!(game.check_region(&region![(0, 1), (1, 1)]).is_ok());
+
+        ruleset.allow_duplicate_words = false;
+        let mut game = Game::new(&board, &ruleset);
+
+        game.add_region(game.check_region(&region![(0, 0), (1, 0)]).unwrap(), ());
+        assert!(matches!(
+            game.check_region(&region![(0, 1), (1, 1)]),
+            Err(CheckRegionError::DuplicateWord)
+        ));
+    }
+
+    #[test]
+    fn game_has_unwinnable_pockets() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        assert!(!game.has_unwinnable_pockets());
+
+        // cutting off a corner leaves a pocket of one square
+        let region = region![(1, 0), (1, 1), (0, 1)];
+        game.add_region(CheckedRegion(&region), ());
+        assert!(game.has_unwinnable_pockets());
+
+        // taking the top row leaves DEF and GHI to be found below it
+        let mut game = Game::new(&board, &ruleset);
+        let region = region![(0, 0), (1, 0), (2, 0)];
+        game.add_region(CheckedRegion(&region), ());
+        assert!(!game.has_unwinnable_pockets());
+
+        // a pocket big enough to hold a word, but without one in it, is just as stuck
+        let mut game = Game::new(&board, &ruleset);
+        let region = region![(1, 0), (1, 1), (1, 2)];
+        game.add_region(CheckedRegion(&region), ());
+        assert!(game.has_unwinnable_pockets());
+    }
+
+    #[test]
+    fn game_region_data_mut() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        game.add_region(
+            game.check_region(&region![(0, 1), (1, 1), (2, 1)]).unwrap(),
+            1,
+        );
+
+        assert_eq!(game.region_data_mut((0, 0).into()), None);
+        *game.region_data_mut((2, 1).into()).unwrap() += 1;
+        assert_eq!(game.regions().next().unwrap().1, 2);
+    }
+
+    #[test]
+    fn game_replace_region() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            1,
+        );
+
+        // ABC can lose its C and still be AB
+        assert_eq!(
+            game.replace_region((2, 0).into(), &region![(0, 0), (1, 0)]),
+            Some(Ok(()))
+        );
+        assert_eq!(game.regions().next(), Some(&(region![(0, 0), (1, 0)], 1)));
+        assert!(game.is_square_free((2, 0).into()));
+
+        // but A on its own is too short, so AB stays
+        assert_eq!(
+            game.replace_region((0, 0).into(), &region![(0, 0)]),
+            Some(Err(CheckRegionError::TooShort))
+        );
+        assert_eq!(game.regions().next(), Some(&(region![(0, 0), (1, 0)], 1)));
+
+        assert_eq!(game.replace_region((2, 2).into(), &region![(2, 2)]), None);
+    }
+
+    #[test]
+    fn game_has_possible_completion() {
+        let board = board();
+        let dictionary = ["ABC", "DEF", "GHI", "DEH"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+        assert!(game.has_possible_completion(100));
+
+        // DEH fits below ABC, but leaves F, G and I apart
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            (),
+        );
+        game.add_region(
+            game.check_region(&region![(0, 1), (1, 1), (1, 2)]).unwrap(),
+            (),
+        );
+        assert!(!game.has_possible_completion(100));
+        game.remove_region((0, 1).into());
+        assert!(game.has_possible_completion(100));
+
+        // with only ABC and DEH it can't be finished, but that takes more than one step to find
+        let dictionary = ["ABC", "DEH"].into_iter().map(|w| w.to_owned()).collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            (),
+        );
+        assert!(!game.has_unwinnable_pockets());
+        assert!(!game.has_possible_completion(100));
+        assert!(game.has_possible_completion(1));
+    }
+
+    #[test]
+    fn game_has_possible_completion_big_board() {
+        // every region of As is a word, so there are thousands of ways to cover each square
+        let board = Board::new(20, "A".repeat(400)).unwrap();
+        let dictionary = (2..=8).map(|length| "A".repeat(length)).collect();
+        let ruleset = Ruleset::new(2, 8, dictionary);
+        let mut game = Game::<()>::new(&board, &ruleset);
+        assert!(game.has_possible_completion(10000));
+
+        // the search gives up once it's used the budget, rather than going on to find the rest
+        let mut budget = 10000;
+        let result = game.search_completions(&mut budget, &mut |_| ControlFlow::Break(()));
+        assert_eq!(result, ControlFlow::Break(None));
+        assert_eq!(budget, 0);
+    }
+
+    #[test]
+    fn game_solve() {
+        let board = board();
+        let dictionary = ["ABC", "DEF", "GHI", "ADG", "BEH", "CFI"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+
+        // rows and columns both work, and the rows are found first every time
+        let rows = vec![
+            region![(0, 0), (1, 0), (2, 0)],
+            region![(0, 1), (1, 1), (2, 1)],
+            region![(0, 2), (1, 2), (2, 2)],
+        ];
+        assert_eq!(game.solve(), Some(rows.clone()));
+        assert_eq!(game.solve(), Some(rows));
+
+        // what's on the board already stays, and the rest is filled in around it
+        let middle = region![(1, 0), (1, 1), (1, 2)];
+        game.add_region(game.check_region(&middle).unwrap(), ());
+        let solution = game.solve().unwrap();
+        assert_eq!(solution[0], middle);
+        let words = solution.iter().map(|region| region.word(&board));
+        assert_eq!(words.collect::<Vec<_>>(), ["BEH", "ADG", "CFI"]);
+
+        let dictionary = ["ABC", "DEH"].into_iter().map(|w| w.to_owned()).collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        assert_eq!(Game::<()>::new(&board, &ruleset).solve(), None);
+    }
+
+    #[test]
+    fn game_hint() {
+        let board = Board::new(4, "CATSDOGX").unwrap();
+        let dictionary = ["CAT", "DOG", "GOD"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let mut ruleset = Ruleset::new(3, 3, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+        assert_eq!(game.hint(), Some(region![(0, 0), (1, 0), (2, 0)]));
+
+        game.add_region(
+            game.check_region(&region![(0, 0), (1, 0), (2, 0)]).unwrap(),
+            (),
+        );
+        assert_eq!(game.hint(), Some(region![(0, 1), (1, 1), (2, 1)]));
+        game.add_region(
+            game.check_region(&region![(0, 1), (1, 1), (2, 1)]).unwrap(),
+            (),
+        );
+        assert_eq!(game.hint(), None);
+
+        // a word which is in the dictionary but isn't straight isn't a hint
+        ruleset.shape = Shape::StraightLine;
+        let board = Board::new(2, "DOXG").unwrap();
+        let game = Game::<()>::new(&board, &ruleset);
+        assert_eq!(game.hint(), None);
+    }
+
+    #[test]
+    fn game_longest_placeable() {
+        let board = Board::new(4, "CATSDOGX").unwrap();
+        let dictionary = ["CAT", "CATS", "DOG", "ATS"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 4, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+        assert_eq!(
+            game.longest_placeable(),
+            Some(region![(0, 0), (1, 0), (2, 0), (3, 0)])
+        );
+
+        // with the S gone, CAT and DOG tie and CAT comes first
+        game.add_region(CheckedRegion(&region![(3, 0), (3, 1)]), ());
+        assert_eq!(
+            game.longest_placeable(),
+            Some(region![(0, 0), (1, 0), (2, 0)])
+        );
+
+        game.add_region(CheckedRegion(&region![(0, 0), (1, 0), (2, 0)]), ());
+        game.add_region(CheckedRegion(&region![(0, 1), (1, 1), (2, 1)]), ());
+        assert_eq!(game.longest_placeable(), None);
+    }
+
+    #[test]
+    fn game_straight_placements() {
+        #[rustfmt::skip]
+        let board = Board::new(
+            3,
+            concat!(
+                "CAT",
+                "ZZS",
+            )
+        )
+        .unwrap();
+        let dictionary = ["CAT", "CATS", "TS"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let mut ruleset = Ruleset::new(2, 4, dictionary);
+        let game = Game::<()>::new(&board, &ruleset);
+        assert_eq!(
+            game.longest_placeable(),
+            Some(region![(0, 0), (1, 0), (2, 0), (2, 1)])
+        );
+        assert_eq!(
+            game.forced_placements(),
+            [
+                region![(0, 0), (1, 0), (2, 0)],
+                region![(0, 0), (1, 0), (2, 0), (2, 1)],
+                region![(2, 0), (2, 1)],
+            ]
+        );
+
+        // CATS bends around the corner, so it's never offered
+        ruleset.shape = Shape::StraightLine;
+        let game = Game::<()>::new(&board, &ruleset);
+        assert_eq!(
+            game.longest_placeable(),
+            Some(region![(0, 0), (1, 0), (2, 0)])
+        );
+        assert_eq!(
+            game.forced_placements(),
+            [region![(0, 0), (1, 0), (2, 0)], region![(2, 0), (2, 1)]]
+        );
+    }
+
+    #[test]
+    fn game_free_letters() {
+        let board = Board::new(3, "CABBAGEEE").unwrap();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        assert_eq!(game.free_letters().iter().collect::<String>(), "AABBCEEEG");
+
+        game.add_region(CheckedRegion(&region![(0, 2), (1, 2), (2, 2)]), ());
+        assert_eq!(game.free_letters().iter().collect::<String>(), "AABBCG");
+    }
+
+    #[test]
+    fn game_is_solved() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        assert!(!game.is_complete());
+        assert!(!game.is_solved());
+
+        for region in [
+            region![(0, 0), (1, 0), (2, 0)],
+            region![(0, 1), (1, 1), (2, 1)],
+            region![(0, 2), (1, 2), (2, 2)],
+        ] {
+            game.add_region(game.check_region(&region).unwrap(), ());
+        }
+        assert!(game.is_complete());
+        assert!(game.is_solved());
+
+        // covering the board with regions which were never checked isn't solving it
+        let mut game = Game::new(&board, &ruleset);
+        for region in [
+            region![(0, 0), (1, 0), (0, 1)],
+            region![(2, 0), (1, 1), (2, 1)],
+            region![(0, 2), (1, 2), (2, 2)],
+        ] {
+            game.add_region(CheckedRegion(&region), ());
+        }
+        assert!(game.is_complete());
+        assert!(!game.is_solved());
+    }
+
+    #[test]
+    fn game_score() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut game = Game::new(&board, &ruleset);
+        assert_eq!(game.score(), 0);
+
+        let region = region![(0, 0), (1, 0)];
+        game.add_region(game.check_region(&region).unwrap(), ());
+        assert_eq!(game.score(), 4);
+
+        let region = region![(0, 1), (1, 1), (2, 1)];
+        game.add_region(game.check_region(&region).unwrap(), ());
+        assert_eq!(game.score(), 13);
+    }
+
+    #[test]
+    fn game_placements() {
+        #[rustfmt::skip]
+        let board = Board::new(
+            3,
+            concat!(
+                "CAT",
+                "ATE",
+            )
+        )
+        .unwrap();
+        let dictionary = ["CAT", "CATATE", "EAT"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 6, dictionary);
+        let mut game = Game::new(&board, &ruleset);
+
+        let mut placements = game.placements();
+        placements.sort_unstable_by(|a, b| a.squares.cmp(&b.squares));
+        assert_eq!(
+            placements,
+            [
+                region![(0, 0), (1, 0), (2, 0)],
+                region![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)],
+                region![(0, 0), (1, 0), (1, 1)],
+                region![(0, 0), (0, 1), (1, 1)],
+            ]
+        );
+
+        let forced = game.forced_placements();
+        assert_eq!(
+            forced,
+            [region![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]]
+        );
+
+        // once CAT takes the top row, nothing left spells a word
+        let region = region![(0, 0), (1, 0), (2, 0)];
+        game.add_region(game.check_region(&region).unwrap(), ());
+        assert!(game.placements().is_empty());
+
+        // but the whole board still has everything
+        assert_eq!(game.all_placements(), placements);
     }
 }
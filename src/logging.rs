@@ -0,0 +1,65 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::error::Error;
+
+/// The environment variable naming the file to log to.
+const LOG_VAR: &str = "PAPERBARK_LOG";
+
+/// Writes every record to a file, since the terminal is taken up by the game.
+struct FileLogger {
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let seconds = self.started.elapsed().as_secs_f64();
+        if let Ok(mut file) = self.file.lock() {
+            // there's nowhere left to report a failure to log
+            let _ = writeln!(
+                file,
+                "{seconds:9.3} {:5} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Starts logging to the file named by `PAPERBARK_LOG`, appending to it if it's already there.
+/// Without it no logger is set and the maximum level stays off, so the log macros give up before
+/// formatting anything.
+pub fn init() -> Result<(), Error> {
+    let path = match env::var_os(LOG_VAR) {
+        Some(path) if !path.is_empty() => path,
+        _ => return Ok(()),
+    };
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let logger = FileLogger {
+        file: Mutex::new(file),
+        started: Instant::now(),
+    };
+    // this only fails if a logger has already been set, which means logging is on anyway
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+
+    Ok(())
+}
@@ -1,22 +1,53 @@
-use chrono::{TimeZone, Utc};
-use itertools::izip;
-use serde::Deserialize;
-use std::error::Error;
+use chrono::{NaiveDate, Utc};
+use itertools::{iproduct, izip};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::io;
-use std::iter;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
-use tui::style::Color;
+
+mod dictionary;
+use dictionary::{Dictionary, DiskDictionary};
+
+mod error;
+use error::Error;
 
 mod game;
 use game::*;
 
+mod logging;
+
+mod rng;
+use rng::Rng;
+
+mod solution;
+use solution::{ShareCode, Solution};
+
+mod solve;
+
+mod storage;
+use storage::Dir;
+
+mod svg;
+
+mod topology;
+
 mod ui;
 
 #[derive(Deserialize, Debug)]
 struct DictionaryData(Vec<String>);
 
+impl DictionaryData {
+    fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let json = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct GameData {
@@ -26,6 +57,291 @@ struct GameData {
     max_size: usize,
     regions: Vec<Vec<(usize, usize)>>,
     words: Vec<String>,
+    // not in every puzzle, but shown if they are
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+}
+
+impl GameData {
+    /// Parses a puzzle, reporting every problem with it rather than just the first.
+    fn parse(json: &str) -> Result<Self, Error> {
+        let parse_error = |error| Error::ParseDocument {
+            document: "game",
+            error,
+        };
+        let value = serde_json::from_str(json).map_err(parse_error)?;
+
+        let problems = Self::field_problems(&value);
+        if !problems.is_empty() {
+            return Err(Error::Validation(problems.join("; ")));
+        }
+
+        let game_data = serde_json::from_value::<GameData>(value).map_err(parse_error)?;
+        game_data
+            .validate()
+            .map_err(|problems| Error::Validation(problems.join("; ")))?;
+
+        Ok(game_data)
+    }
+
+    /// Checks the fields are all there and of the right type, which serde would only report one at
+    /// a time, and not very clearly.
+    fn field_problems(value: &serde_json::Value) -> Vec<String> {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return vec!["the puzzle isn't a JSON object".to_owned()],
+        };
+
+        let mut problems = vec![];
+        for key in ["width", "height", "minSize", "maxSize", "regions", "words"] {
+            match object.get(key) {
+                None => problems.push(format!("missing {key}")),
+                Some(value) if key.ends_with("Size") || key == "width" || key == "height" => {
+                    if !value.is_u64() {
+                        problems.push(format!("{key} isn't a whole number: {value}"));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        let regions = object.get("regions").and_then(|regions| regions.as_array());
+        for (i, region) in regions.into_iter().flatten().enumerate() {
+            let squares = match region.as_array() {
+                Some(squares) => squares,
+                None => {
+                    problems.push(format!("region {} isn't a list of squares", i + 1));
+                    continue;
+                }
+            };
+
+            for square in squares {
+                let is_coordinate_pair = square
+                    .as_array()
+                    .is_some_and(|pair| pair.len() == 2 && pair.iter().all(|n| n.is_u64()));
+                if !is_coordinate_pair {
+                    problems.push(format!(
+                        "region {} has a square which isn't a pair of whole numbers: {square}",
+                        i + 1
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Checks the words fit their regions, and the regions exactly cover the board.
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let GameData {
+            width,
+            height,
+            min_size,
+            max_size,
+            regions,
+            words,
+            ..
+        } = self;
+        let mut problems = vec![];
+
+        if *width == 0 || *height == 0 {
+            problems.push("the board has no squares".to_owned());
+        }
+        if min_size > max_size {
+            problems.push(format!(
+                "minSize {min_size} is more than maxSize {max_size}"
+            ));
+        }
+        if regions.len() != words.len() {
+            problems.push(format!(
+                "{} regions but {} words",
+                regions.len(),
+                words.len()
+            ));
+        }
+
+        let mut coverage = vec![0; width * height];
+        for (word, region) in izip!(words, regions) {
+            if word.chars().count() != region.len() {
+                problems.push(format!(
+                    "{word} doesn't fit in a region of {} squares",
+                    region.len()
+                ));
+            }
+
+            for &(x, y) in region {
+                if x >= *width || y >= *height {
+                    problems.push(format!(
+                        "{word} has a letter outside the board at ({x}, {y})"
+                    ));
+                } else {
+                    coverage[y * width + x] += 1;
+                }
+            }
+        }
+
+        for (i, &count) in coverage.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            match count {
+                0 => problems.push(format!("no word covers ({x}, {y})")),
+                1 => {}
+                _ => problems.push(format!("{count} words overlap at ({x}, {y})")),
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd(2022, 5, 6)
+}
+
+fn puzzle_id_for_date(date: NaiveDate) -> i64 {
+    date.signed_duration_since(epoch()).num_days() + 1
+}
+
+fn date_for_puzzle_id(puzzle_id: i64) -> NaiveDate {
+    epoch() + chrono::Duration::days(puzzle_id - 1)
+}
+
+fn today() -> NaiveDate {
+    Utc::today().naive_utc()
+}
+
+/// The puzzle for `date`, as long as it's been released by `today`.
+fn date_puzzle_id(date: NaiveDate, today: NaiveDate) -> Result<i64, Error> {
+    if date < epoch() {
+        let message = format!("there are no puzzles before {}", epoch());
+        return Err(Error::Validation(message));
+    }
+
+    if date > today {
+        let message = format!("the puzzle for {date} hasn't been released yet");
+        return Err(Error::Validation(message));
+    }
+
+    Ok(puzzle_id_for_date(date))
+}
+
+const BASE_URL: &str = "https://www.andrewt.net/puzzles/cell-tower";
+
+/// How long a downloaded puzzle is used before it's downloaded again, in case it's been fixed.
+const PUZZLE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long the downloaded dictionary is used before asking the server whether it's changed.
+const DICTIONARY_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+fn download(client: &reqwest::blocking::Client, path: &str) -> Result<String, Error> {
+    log::debug!("downloading {path}");
+    Ok(client
+        .get(format!("{BASE_URL}/{path}"))
+        .send()?
+        .error_for_status()?
+        .text()?)
+}
+
+/// What the server told us about the cached dictionary, so we can ask whether it's changed.
+#[derive(Serialize, Deserialize, Default)]
+struct DictionaryValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Gets the dictionary, which is big and rarely changes, from the cache if we have it. With
+/// `refresh`, or once the cached copy is old, the server is asked for it anyway, but only sends it
+/// again if it's changed, and the cached copy is still used if the server can't be reached. With
+/// `no_cache` it's downloaded again whatever's cached.
+fn fetch_dictionary(
+    client: &reqwest::blocking::Client,
+    refresh: bool,
+    no_cache: bool,
+) -> Result<String, Error> {
+    let name = storage::dictionary_name();
+    let cached = if no_cache {
+        None
+    } else {
+        Dir::Cache.read(&name).ok()
+    };
+    let stale = Dir::Cache
+        .age(&name)
+        .is_none_or(|age| age > DICTIONARY_MAX_AGE);
+    if let (Some(dictionary_json), false) = (&cached, refresh || stale) {
+        log::debug!("using the cached dictionary");
+        return Ok(dictionary_json.clone());
+    }
+
+    match download_dictionary(client, cached.as_deref()) {
+        Ok(dictionary_json) => Ok(dictionary_json),
+        Err(e) => match cached {
+            Some(dictionary_json) => {
+                eprintln!(
+                    "warning: couldn't check for a newer dictionary, using the cached one: {e}"
+                );
+                Ok(dictionary_json)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Downloads the dictionary, unless it hasn't changed since the `cached` copy was downloaded.
+fn download_dictionary(
+    client: &reqwest::blocking::Client,
+    cached: Option<&str>,
+) -> Result<String, Error> {
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+    let name = storage::dictionary_name();
+    let mut request = client.get(format!("{BASE_URL}/assets/words.json"));
+    if cached.is_some() {
+        let validators = Dir::Cache
+            .read(&storage::dictionary_validators_name())
+            .ok()
+            .and_then(|json| serde_json::from_str::<DictionaryValidators>(&json).ok())
+            .unwrap_or_default();
+
+        if let Some(etag) = validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send()?;
+    if let (reqwest::StatusCode::NOT_MODIFIED, Some(dictionary_json)) = (response.status(), cached)
+    {
+        log::debug!("the cached dictionary is still current");
+        // write it back, so it's a while before the server is asked again
+        let _ = Dir::Cache.write(&name, dictionary_json);
+        return Ok(dictionary_json.to_owned());
+    }
+
+    let response = response.error_for_status()?;
+    let header = |name| {
+        let value = response.headers().get(name)?;
+        value.to_str().ok().map(str::to_owned)
+    };
+    let validators = DictionaryValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    let dictionary_json = response.text()?;
+    log::debug!("downloaded a new dictionary");
+
+    // keep a copy around for next time, but don't fail if we can't
+    let _ = Dir::Cache.write(&name, &dictionary_json);
+    if let Ok(validators_json) = serde_json::to_string(&validators) {
+        let _ = Dir::Cache.write(&storage::dictionary_validators_name(), &validators_json);
+    }
+
+    Ok(dictionary_json)
 }
 
 struct OfficialData {
@@ -37,47 +353,97 @@ impl OfficialData {
     fn from_paths<P1: AsRef<Path>, P2: AsRef<Path>>(
         dictionary_path: P1,
         game_path: P2,
-    ) -> io::Result<Self> {
+    ) -> Result<Self, Error> {
         let dictionary_json = fs::read_to_string(dictionary_path)?;
         let game_json = fs::read_to_string(game_path)?;
 
-        Ok(Self::from_json(&dictionary_json, &game_json))
+        Self::from_json(&dictionary_json, &game_json)
     }
 
-    fn from_web_today() -> reqwest::Result<Self> {
-        let epoch = Utc.ymd(2022, 5, 6).and_hms(0, 0, 0);
-        let puzzle_id = Utc::now().signed_duration_since(epoch).num_days() + 1;
+    /// Downloads a puzzle, or uses the copy downloaded last time if it's recent enough and
+    /// `no_cache` isn't set. An older copy is still used if the puzzle can't be downloaded.
+    fn from_web(puzzle_id: i64, refresh_dictionary: bool, no_cache: bool) -> Result<Self, Error> {
+        let client = reqwest::blocking::Client::new();
+        let dictionary_json = fetch_dictionary(&client, refresh_dictionary, no_cache)?;
+
+        let name = storage::puzzle_name(puzzle_id);
+        let cached = if no_cache {
+            None
+        } else {
+            Dir::Cache.read(&name).ok()
+        };
+        let fresh = Dir::Cache
+            .age(&name)
+            .is_some_and(|age| age <= PUZZLE_MAX_AGE);
+        let game_json = match (cached, fresh) {
+            (Some(game_json), true) => {
+                log::debug!("using the cached copy of puzzle {puzzle_id}");
+                game_json
+            }
+            (cached, _) => match download(&client, &format!("puzzles/{puzzle_id}.json")) {
+                Ok(game_json) => {
+                    // keep a copy around for next time and --offline, but don't fail if we can't
+                    let _ = Dir::Cache.write(&name, &game_json);
+                    game_json
+                }
+                Err(e) => match cached {
+                    Some(game_json) => {
+                        eprintln!(
+                            "warning: couldn't download puzzle {puzzle_id} again, using the cached copy: {e}"
+                        );
+                        game_json
+                    }
+                    None => return Err(e),
+                },
+            },
+        };
 
-        Self::from_web(puzzle_id)
+        Self::from_json(&dictionary_json, &game_json)
     }
 
-    fn from_web(puzzle_id: i64) -> reqwest::Result<Self> {
-        const BASE_URL: &str = "https://www.andrewt.net/puzzles/cell-tower";
+    fn from_cache(puzzle_id: i64) -> Result<Self, Error> {
+        let not_cached = |e: io::Error| {
+            let message = format!("puzzle {puzzle_id} isn't available offline ({e})");
+            io::Error::new(e.kind(), message)
+        };
 
-        let client = reqwest::blocking::Client::new();
-        let dictionary_json = client
-            .get(format!("{BASE_URL}/assets/words.json"))
-            .send()?
-            .text()?;
-        let game_json = client
-            .get(format!("{BASE_URL}/puzzles/{puzzle_id}.json"))
-            .send()?
-            .text()?;
+        let dictionary_json = Dir::Cache
+            .read(&storage::dictionary_name())
+            .map_err(not_cached)?;
+        let game_json = Dir::Cache
+            .read(&storage::puzzle_name(puzzle_id))
+            .map_err(not_cached)?;
 
-        Ok(Self::from_json(&dictionary_json, &game_json))
+        Self::from_json(&dictionary_json, &game_json)
     }
 
-    fn from_json(dictionary_json: &str, game_json: &str) -> Self {
-        let dictionary_data = serde_json::from_str(dictionary_json).unwrap();
-        let game_data = serde_json::from_str(game_json).unwrap();
+    fn from_json(dictionary_json: &str, game_json: &str) -> Result<Self, Error> {
+        let dictionary_data =
+            serde_json::from_str(dictionary_json).map_err(|error| Error::ParseDocument {
+                document: "dictionary",
+                error,
+            })?;
+        let game_data = GameData::parse(game_json)?;
 
-        Self {
+        Ok(Self {
             dictionary_data,
             game_data,
+        })
+    }
+
+    fn extend_dictionary(&mut self, words: impl IntoIterator<Item = String>) {
+        self.dictionary_data.0.extend(words);
+    }
+
+    /// Takes the hyphens and spaces out of dictionary entries like ICE-CREAM, since a region can
+    /// only ever spell the letters.
+    fn join_dictionary_words(&mut self) {
+        for word in &mut self.dictionary_data.0 {
+            word.retain(|c| c != '-' && c != ' ');
         }
     }
 
-    fn board(&self) -> Board {
+    fn board(&self) -> Result<Board, Error> {
         let GameData {
             width,
             height,
@@ -86,26 +452,36 @@ impl OfficialData {
             ..
         } = &self.game_data;
 
-        let empty_chars = iter::repeat(None).take(width * height).collect::<Vec<_>>();
-
-        assert_eq!(regions.len(), words.len());
-        let chars = izip!(words, regions).fold(empty_chars, |mut chars, (word, region)| {
-            assert_eq!(word.len(), region.len());
-
-            for (c, (x, y)) in izip!(word.chars(), region) {
-                let char_slot = &mut chars[y * width + x];
-                assert_eq!(*char_slot, None);
-                *char_slot = Some(c.to_uppercase().next().unwrap());
+        // the puzzle was validated when it was parsed, so every square has exactly one letter
+        let mut chars = vec![' '; width * height];
+        for (word, region) in izip!(words, regions) {
+            for (c, &(x, y)) in izip!(word.chars(), region) {
+                chars[y * width + x] = c.to_uppercase().next().unwrap_or(c);
             }
-
-            chars
-        });
-
-        let chars = chars.into_iter().map(Option::unwrap).collect::<String>();
+        }
+        let chars = chars.into_iter().collect::<String>();
 
         Board::new(*width, chars)
     }
 
+    fn puzzle(&self, puzzle_id: Option<i64>) -> ui::Puzzle {
+        ui::Puzzle {
+            id: puzzle_id,
+            date: puzzle_id.map(date_for_puzzle_id),
+            title: self.game_data.title.clone(),
+            author: self.game_data.author.clone(),
+            words: self.game_data.words.len(),
+            solution: self
+                .game_data
+                .regions
+                .iter()
+                .map(|region| region.iter().map(|&s| s.into()).collect())
+                .collect(),
+            median_time: None,
+            progress: None,
+        }
+    }
+
     fn ruleset(&self) -> Ruleset {
         let dictionary = self
             .dictionary_data
@@ -114,36 +490,1303 @@ impl OfficialData {
             .map(|w| w.to_uppercase())
             .collect();
 
-        Ruleset {
-            min_length: self.game_data.min_size,
-            max_length: self.game_data.max_size,
-            dictionary,
+        Ruleset::new(self.game_data.min_size, self.game_data.max_size, dictionary)
+    }
+}
+
+#[derive(StructOpt)]
+struct LoadOptions {
+    /// Add the words from a JSON word list to the dictionary (can be repeated)
+    #[structopt(long = "dict", global = true, number_of_values = 1)]
+    dictionaries: Vec<PathBuf>,
+    /// Add a single word to the dictionary (can be repeated)
+    #[structopt(long = "extra-word", global = true, number_of_values = 1)]
+    extra_words: Vec<String>,
+    /// Never touch the network, only use previously downloaded puzzles
+    #[structopt(long, global = true)]
+    offline: bool,
+    /// Check for a newer dictionary rather than using the one downloaded before
+    #[structopt(long = "refresh-dict", global = true)]
+    refresh_dictionary: bool,
+    /// Download the puzzle and dictionary again rather than using copies downloaded before
+    #[structopt(long, global = true, conflicts_with = "offline")]
+    no_cache: bool,
+    /// Match dictionary entries with hyphens or spaces in them by their letters alone
+    #[structopt(long, global = true)]
+    join_words: bool,
+    /// Look words up in a sorted list on disk instead of keeping the dictionary in memory
+    #[structopt(long, global = true)]
+    low_memory: bool,
+}
+
+impl LoadOptions {
+    fn load(&self, puzzle_id: i64) -> Result<OfficialData, Error> {
+        log::info!("loading puzzle {puzzle_id} (offline: {})", self.offline);
+        let mut official_data = if self.offline {
+            OfficialData::from_cache(puzzle_id)?
+        } else {
+            OfficialData::from_web(puzzle_id, self.refresh_dictionary, self.no_cache)?
+        };
+        self.extend_dictionary(&mut official_data)?;
+
+        Ok(official_data)
+    }
+
+    /// Loads a puzzle given either its id or the path to its file.
+    fn load_id_or_file(&self, puzzle: &str) -> Result<OfficialData, Error> {
+        match puzzle.parse::<i64>() {
+            Ok(puzzle_id) => self.load(puzzle_id),
+            Err(_) => self.load_file(Path::new(puzzle)),
         }
     }
+
+    /// Loads a puzzle from a file, with the official dictionary.
+    fn load_file(&self, path: &Path) -> Result<OfficialData, Error> {
+        log::info!("loading puzzle from {}", path.display());
+        self.load_json(&fs::read_to_string(path)?)
+    }
+
+    /// Loads a puzzle and its dictionary from files, checking they're there first so a mistyped
+    /// path is reported as such.
+    fn load_paths(&self, dictionary: &Path, game: &Path) -> Result<OfficialData, Error> {
+        let dictionary = input_path(dictionary)?;
+        let game = input_path(game)?;
+        log::info!(
+            "loading puzzle from {} with dictionary {}",
+            game.display(),
+            dictionary.display()
+        );
+        let mut official_data = OfficialData::from_paths(dictionary, game)?;
+        self.extend_dictionary(&mut official_data)?;
+
+        Ok(official_data)
+    }
+
+    /// Loads a puzzle piped in on stdin, with the official dictionary. All of stdin is read, so the
+    /// terminal is free for the game afterwards.
+    fn load_stdin(&self) -> Result<OfficialData, Error> {
+        log::info!("loading puzzle from stdin");
+        let mut game_json = String::new();
+        io::stdin().read_to_string(&mut game_json)?;
+
+        self.load_json(&game_json)
+    }
+
+    fn load_json(&self, game_json: &str) -> Result<OfficialData, Error> {
+        let dictionary_json = if self.offline {
+            Dir::Cache.read(&storage::dictionary_name())?
+        } else {
+            let client = reqwest::blocking::Client::new();
+            fetch_dictionary(&client, self.refresh_dictionary, self.no_cache)?
+        };
+
+        let mut official_data = OfficialData::from_json(&dictionary_json, game_json)?;
+        self.extend_dictionary(&mut official_data)?;
+
+        Ok(official_data)
+    }
+
+    /// Builds the ruleset for a loaded puzzle. With --low-memory, the puzzle's dictionary is moved
+    /// out of memory and onto disk.
+    fn ruleset(
+        &self,
+        official_data: &mut OfficialData,
+        config: &ui::Config,
+    ) -> Result<Ruleset, Error> {
+        if self.join_words {
+            official_data.join_dictionary_words();
+        }
+
+        let mut ruleset = if self.low_memory {
+            // the dictionary can include words from --dict, so it has to be written out each time
+            let words = std::mem::take(&mut official_data.dictionary_data.0);
+            let path = Dir::Cache.writable_path(&storage::sorted_dictionary_name())?;
+            DiskDictionary::write(&path, words.into_iter().map(|w| w.to_uppercase()))?;
+
+            let dictionary = Dictionary::Disk(DiskDictionary::open(&path)?);
+            let GameData {
+                min_size, max_size, ..
+            } = official_data.game_data;
+            Ruleset::new(min_size, max_size, dictionary)
+        } else {
+            official_data.ruleset()
+        };
+        config.configure_ruleset(&mut ruleset);
+
+        Ok(ruleset)
+    }
+
+    fn extend_dictionary(&self, official_data: &mut OfficialData) -> Result<(), Error> {
+        for path in &self.dictionaries {
+            official_data.extend_dictionary(DictionaryData::from_path(path)?.0);
+        }
+        official_data.extend_dictionary(self.extra_words.iter().cloned());
+
+        if official_data.dictionary_data.0.is_empty() {
+            eprintln!("warning: the dictionary is empty, so no words will be accepted");
+        }
+
+        if !self.dictionaries.is_empty() || !self.extra_words.is_empty() {
+            eprintln!("loaded {} words", official_data.ruleset().dictionary.len());
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(StructOpt)]
 #[structopt(about = "a terminal-based clone of the cell tower puzzle game")]
-enum Paperbark {
+struct Paperbark {
+    #[structopt(flatten)]
+    config: ui::Config,
+    #[structopt(flatten)]
+    load_options: LoadOptions,
+    /// Print the words of the committed regions to stdout on exit
+    #[structopt(long, global = true)]
+    print_words: bool,
+    /// Print the output of --print-words or dump as JSON
+    #[structopt(long, global = true)]
+    json: bool,
+    /// Include how far into the game each word was placed with --print-words
+    #[structopt(long, global = true, requires = "print-words")]
+    timings: bool,
+    /// Print how the game went as JSON on exit: whether it was completed, the score, the time and
+    /// the number of regions
+    #[structopt(long, global = true)]
+    result: bool,
+    /// Start the puzzle over rather than picking up where it was left off
+    #[structopt(long, global = true)]
+    fresh: bool,
+    /// Write the committed regions to a solution file on exit, which can be drawn with render
+    #[structopt(long, global = true)]
+    save_solution: Option<PathBuf>,
+    /// Print a short code for the committed regions on exit, which can be looked at with shared
+    #[structopt(long, global = true)]
+    share_code: bool,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
     Today,
-    Day { puzzle_id: u64 },
+    Day {
+        puzzle_id: u64,
+    },
+    /// Play the puzzle released on a date (YYYY-MM-DD)
+    Date {
+        date: NaiveDate,
+    },
+    /// Play a run of puzzles back to back
+    Marathon {
+        #[structopt(long)]
+        from: i64,
+        #[structopt(long)]
+        to: i64,
+    },
+    /// Solve a range of puzzles without the ui, timing the solver on each
+    Autoplay {
+        #[structopt(long)]
+        from: i64,
+        #[structopt(long)]
+        to: i64,
+        /// Play each solution through the game to check it solves the puzzle
+        #[structopt(long)]
+        verify: bool,
+    },
+    /// Download a range of puzzles so they can be played with --offline
+    Prefetch {
+        #[structopt(long)]
+        from: i64,
+        #[structopt(long)]
+        to: i64,
+    },
+    /// Print how a puzzle (by id, or from a file) was parsed, without playing it
+    Dump {
+        puzzle: String,
+    },
+    /// Analyse a puzzle (by id, or from a file), such as whether it has a unique solution
+    Info {
+        puzzle: String,
+    },
+    /// Print a solution to a puzzle (by id, or from a file) as JSON, in the form --save-solution
+    /// writes with each region's word added, without playing it
+    Solve {
+        puzzle: String,
+    },
+    /// Print what changed between two puzzles (by id, or from files): the board's size, its
+    /// letters, and its regions and words
+    Diff {
+        a: String,
+        b: String,
+    },
+    /// Print the id and date of today's puzzle, or the one released on a date, without fetching it
+    Id {
+        date: Option<NaiveDate>,
+    },
+    /// Play a puzzle read as JSON from stdin, such as one made by another tool
+    Stdin,
+    /// Play a puzzle from files saved earlier, with the dictionary to play it with, without
+    /// touching the network
+    File {
+        dictionary: PathBuf,
+        game: PathBuf,
+    },
+    /// List the words of a code printed by --share-code, and draw them as an SVG image with --out
+    Shared {
+        code: ShareCode,
+        #[structopt(long)]
+        out: Option<PathBuf>,
+    },
+    /// Draw a solution file written by --save-solution as an SVG image
+    Render {
+        solution: PathBuf,
+        #[structopt(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct MarathonResult {
+    from: i64,
+    to: i64,
+    completed: usize,
+    skipped: usize,
+    score: usize,
+    seconds: u64,
+}
+
+impl MarathonResult {
+    const FILE_NAME: &'static str = "marathons.json";
+
+    fn save(self) -> Result<(), Error> {
+        let mut results: Vec<MarathonResult> = match Dir::Data.read(Self::FILE_NAME) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        results.push(self);
+
+        Dir::Data.write(Self::FILE_NAME, &serde_json::to_string_pretty(&results)?)?;
+
+        Ok(())
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let opt = Paperbark::from_args();
-    let (board, ruleset) = match opt {
-        Paperbark::Today => {
-            let official_data = OfficialData::from_web_today()?;
-            (official_data.board(), official_data.ruleset())
+/// How long each puzzle took every time it was completed.
+#[derive(Serialize, Deserialize, Default)]
+struct SolveTimes(BTreeMap<i64, Vec<u64>>);
+
+impl SolveTimes {
+    const FILE_NAME: &'static str = "times.json";
+
+    fn load() -> Result<Self, Error> {
+        match Dir::Data.read(Self::FILE_NAME) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
         }
-        Paperbark::Day { puzzle_id } => {
-            let official_data = OfficialData::from_web(puzzle_id as i64)?;
-            (official_data.board(), official_data.ruleset())
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        Dir::Data.write(Self::FILE_NAME, &serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    fn record(&mut self, puzzle_id: i64, elapsed: Duration) {
+        self.0.entry(puzzle_id).or_default().push(elapsed.as_secs());
+    }
+
+    /// The median time the puzzle has taken, which one unusually slow attempt doesn't throw off.
+    fn median(&self, puzzle_id: i64) -> Option<Duration> {
+        let mut times = self.0.get(&puzzle_id)?.clone();
+        times.sort_unstable();
+
+        let middle = times.len() / 2;
+        let seconds = match times.len() {
+            0 => return None,
+            n if n % 2 == 0 => (times[middle - 1] + times[middle]) / 2,
+            _ => times[middle],
+        };
+
+        Some(Duration::from_secs(seconds))
+    }
+}
+
+fn run_marathon(
+    from: i64,
+    to: i64,
+    config: ui::Config,
+    load_options: &LoadOptions,
+) -> Result<(), Error> {
+    if from > to {
+        let message = format!("can't run a marathon from puzzle {from} back to {to}");
+        return Err(Error::Validation(message));
+    }
+
+    let mut result = MarathonResult {
+        from,
+        to,
+        completed: 0,
+        skipped: 0,
+        score: 0,
+        seconds: 0,
+    };
+
+    let puzzles = (to - from + 1) as usize;
+    for (i, puzzle_id) in (from..=to).enumerate() {
+        let mut official_data = load_options.load(puzzle_id)?;
+        let board = official_data.board()?;
+        let ruleset = load_options.ruleset(&mut official_data, &config)?;
+
+        let marathon = ui::Marathon {
+            puzzle: i + 1,
+            puzzles,
+            score: result.score,
+        };
+        let game = config.new_game(&board, &ruleset)?;
+        let outcome = ui::run(
+            game,
+            official_data.puzzle(Some(puzzle_id)),
+            config.clone(),
+            Some(marathon),
+        )?;
+
+        result.seconds += outcome.elapsed.as_secs();
+        match outcome.ending {
+            ui::Ending::Completed => {
+                result.completed += 1;
+                result.score += outcome.game.score();
+            }
+            ui::Ending::Skipped => result.skipped += 1,
+            ui::Ending::Quit => break,
+        }
+    }
+
+    let MarathonResult {
+        completed,
+        skipped,
+        score,
+        seconds,
+        ..
+    } = result;
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+    println!(
+        "completed {completed} of {puzzles} puzzles ({skipped} skipped), score {score}, time {minutes:02}:{seconds:02}"
+    );
+
+    result.save()
+}
+
+#[derive(Serialize)]
+struct DumpRegion {
+    squares: Vec<(usize, usize)>,
+    word: String,
+    // what the squares actually spell, which is what the game checks against the dictionary
+    reading_order_word: String,
+}
+
+#[derive(Serialize)]
+struct Dump {
+    width: usize,
+    height: usize,
+    rows: Vec<String>,
+    min_length: usize,
+    max_length: usize,
+    dictionary_size: usize,
+    regions: Vec<DumpRegion>,
+}
+
+impl Dump {
+    fn new(official_data: &OfficialData) -> Result<Self, Error> {
+        let board = official_data.board()?;
+        let ruleset = official_data.ruleset();
+
+        let rows = (0..board.height())
+            .map(|y| {
+                (0..board.width())
+                    .map(|x| board.letter((x, y).into()))
+                    .collect()
+            })
+            .collect();
+
+        let GameData { regions, words, .. } = &official_data.game_data;
+        let regions = izip!(regions, words)
+            .map(|(squares, word)| {
+                let region = squares.iter().map(|&s| s.into()).collect::<Region>();
+
+                DumpRegion {
+                    squares: squares.clone(),
+                    word: word.to_uppercase(),
+                    reading_order_word: region.word(&board),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            width: board.width(),
+            height: board.height(),
+            rows,
+            min_length: ruleset.min_length,
+            max_length: ruleset.max_length,
+            dictionary_size: ruleset.dictionary.len(),
+            regions,
+        })
+    }
+
+    fn print(&self) {
+        println!("board {}x{}", self.width, self.height);
+        for row in &self.rows {
+            println!("    {row}");
+        }
+
+        println!(
+            "words of {} to {} letters, {} in the dictionary",
+            self.min_length, self.max_length, self.dictionary_size
+        );
+
+        println!("{} regions", self.regions.len());
+        for region in &self.regions {
+            let squares = region
+                .squares
+                .iter()
+                .map(|(x, y)| format!("({x}, {y})"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let note = if region.word == region.reading_order_word {
+                String::new()
+            } else {
+                format!(" (reads as {})", region.reading_order_word)
+            };
+
+            println!("    {}{note}: {squares}", region.word);
+        }
+    }
+}
+
+fn dump(puzzle: &str, load_options: &LoadOptions, json: bool) -> Result<(), Error> {
+    let official_data = load_options.load_id_or_file(puzzle)?;
+
+    let dump = Dump::new(&official_data)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&dump)?);
+    } else {
+        dump.print();
+    }
+
+    Ok(())
+}
+
+fn info(puzzle: &str, config: &ui::Config, load_options: &LoadOptions) -> Result<(), Error> {
+    let mut official_data = load_options.load_id_or_file(puzzle)?;
+    let board = official_data.board()?;
+    let ruleset = load_options.ruleset(&mut official_data, config)?;
+
+    // there's no need to find every solution, two is enough to know it isn't unique
+    let count = solve::count_solutions(&board, &ruleset, 2);
+    let solutions = match count {
+        0 => "none",
+        1 => "1",
+        _ => "2 or more",
+    };
+    let unique = if count == 1 { "yes" } else { "no" };
+
+    println!("solutions: {solutions}");
+    println!("unique solution: {unique}");
+
+    Ok(())
+}
+
+fn diff(a: &str, b: &str, load_options: &LoadOptions) -> Result<(), Error> {
+    let a = load_options.load_id_or_file(a)?;
+    let b = load_options.load_id_or_file(b)?;
+
+    let differences = diff_puzzles(&a, &b)?;
+    if differences.is_empty() {
+        println!("no differences");
+    }
+    for line in differences {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// Lists how puzzle `b` differs from puzzle `a`, a line each: changes to the board's size and word
+/// lengths, each square whose letter changed, and each region removed (-), added (+), or given a
+/// different word (~).
+fn diff_puzzles(a: &OfficialData, b: &OfficialData) -> Result<Vec<String>, Error> {
+    let (board_a, board_b) = (a.board()?, b.board()?);
+    let (game_a, game_b) = (&a.game_data, &b.game_data);
+    let mut lines = vec![];
+
+    let size = |board: &Board| format!("{}x{}", board.width(), board.height());
+    if (board_a.width(), board_a.height()) != (board_b.width(), board_b.height()) {
+        lines.push(format!("size: {} -> {}", size(&board_a), size(&board_b)));
+    }
+    if (game_a.min_size, game_a.max_size) != (game_b.min_size, game_b.max_size) {
+        lines.push(format!(
+            "word lengths: {}-{} -> {}-{}",
+            game_a.min_size, game_a.max_size, game_b.min_size, game_b.max_size
+        ));
+    }
+
+    // squares on only one of the boards are shown against a blank on the other
+    let letter = |board: &Board, x: usize, y: usize| board.get((x, y).into());
+    let width = board_a.width().max(board_b.width());
+    let height = board_a.height().max(board_b.height());
+    for (y, x) in iproduct!(0..height, 0..width) {
+        let (from, to) = (letter(&board_a, x, y), letter(&board_b, x, y));
+        if from != to {
+            let show = |c: Option<char>| c.map_or("-".to_owned(), String::from);
+            lines.push(format!("({x}, {y}): {} -> {}", show(from), show(to)));
+        }
+    }
+
+    // regions are matched up by their squares, whatever order they're listed in
+    let regions = |game_data: &GameData| {
+        izip!(&game_data.regions, &game_data.words)
+            .map(|(squares, word)| {
+                let mut squares = squares.clone();
+                squares.sort_unstable_by_key(|&(x, y)| (y, x));
+                (squares, word.to_uppercase())
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let (regions_a, regions_b) = (regions(game_a), regions(game_b));
+    let describe = |squares: &[(usize, usize)]| {
+        let squares = squares.iter().map(|(x, y)| format!("({x}, {y})"));
+        squares.collect::<Vec<_>>().join(" ")
+    };
+    for (squares, word) in &regions_a {
+        match regions_b.get(squares) {
+            None => lines.push(format!("- {word}: {}", describe(squares))),
+            Some(new_word) if new_word != word => {
+                lines.push(format!("~ {word} -> {new_word}: {}", describe(squares)));
+            }
+            Some(_) => {}
+        }
+    }
+    for (squares, word) in &regions_b {
+        if !regions_a.contains_key(squares) {
+            lines.push(format!("+ {word}: {}", describe(squares)));
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Runs the solver over a range of puzzles, printing how long each took, and with `verify` plays
+/// the solution it finds through the game. Returns whether every puzzle which loaded was solved.
+fn autoplay(
+    from: i64,
+    to: i64,
+    verify: bool,
+    config: &ui::Config,
+    load_options: &LoadOptions,
+) -> Result<bool, Error> {
+    if from > to {
+        let message = format!("can't autoplay from puzzle {from} back to {to}");
+        return Err(Error::Validation(message));
+    }
+
+    let (mut solved, mut failed, mut missing) = (0, 0, 0);
+    let mut total = Duration::ZERO;
+    let mut slowest: Option<(i64, Duration)> = None;
+    for puzzle_id in from..=to {
+        // a puzzle that won't load is reported, but doesn't stop the rest
+        let loaded = load_options.load(puzzle_id).and_then(|mut official_data| {
+            let board = official_data.board()?;
+            let ruleset = load_options.ruleset(&mut official_data, config)?;
+            Ok((board, ruleset))
+        });
+        let (board, ruleset) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("puzzle {puzzle_id}: couldn't load ({e})");
+                missing += 1;
+                continue;
+            }
+        };
+
+        let started = Instant::now();
+        let solution = solve::find_solution(&board, &ruleset);
+        let elapsed = started.elapsed();
+        total += elapsed;
+        if slowest.is_none_or(|(_, slowest)| elapsed > slowest) {
+            slowest = Some((puzzle_id, elapsed));
+        }
+
+        let seconds = elapsed.as_secs_f64();
+        let result = match solution {
+            None => Err("no solution found".to_owned()),
+            Some(regions) if verify => play_solution(&board, &ruleset, &regions),
+            Some(_) => Ok(()),
+        };
+        match result {
+            Ok(()) => {
+                solved += 1;
+                println!("puzzle {puzzle_id}: solved in {seconds:.3}s");
+            }
+            Err(problem) => {
+                failed += 1;
+                println!("puzzle {puzzle_id}: {problem} after {seconds:.3}s");
+            }
+        }
+    }
+
+    let puzzles = to - from + 1;
+    println!(
+        "solved {solved} of {puzzles} puzzles ({failed} failed, {missing} couldn't be loaded) in {:.3}s",
+        total.as_secs_f64()
+    );
+    if let Some((puzzle_id, slowest)) = slowest {
+        println!(
+            "slowest was puzzle {puzzle_id} at {:.3}s",
+            slowest.as_secs_f64()
+        );
+    }
+
+    Ok(failed == 0)
+}
+
+/// Places `regions` on a new game one at a time, as a player would, and checks they solve it.
+fn play_solution(board: &Board, ruleset: &Ruleset, regions: &[Region]) -> Result<(), String> {
+    let mut game = Game::<()>::new(board, ruleset);
+    for region in regions {
+        let checked_region = game
+            .check_region(region)
+            .map_err(|e| format!("the game rejected {} ({e:?})", region.word(board)))?;
+        game.add_region(checked_region, ());
+    }
+
+    if game.is_solved() {
+        Ok(())
+    } else {
+        Err("the solution didn't solve the game".to_owned())
+    }
+}
+
+fn prefetch(from: i64, to: i64, load_options: &LoadOptions) -> Result<(), Error> {
+    if from > to {
+        let message = format!("can't prefetch from puzzle {from} back to {to}");
+        return Err(Error::Validation(message));
+    }
+
+    let client = reqwest::blocking::Client::new();
+
+    let LoadOptions {
+        refresh_dictionary,
+        no_cache,
+        ..
+    } = *load_options;
+    if !Dir::Cache.contains(&storage::dictionary_name()) || refresh_dictionary || no_cache {
+        eprintln!("downloading dictionary");
+        fetch_dictionary(&client, refresh_dictionary, no_cache)?;
+    }
+
+    let (mut downloaded, mut cached, mut missing) = (0, 0, 0);
+    let puzzles = to - from + 1;
+    for (i, puzzle_id) in (from..=to).enumerate() {
+        let progress = format!("[{}/{puzzles}] puzzle {puzzle_id}", i + 1);
+        let name = storage::puzzle_name(puzzle_id);
+
+        if Dir::Cache.contains(&name) && !no_cache {
+            eprintln!("{progress}: already cached");
+            cached += 1;
+            continue;
+        }
+
+        match download(&client, &format!("puzzles/{puzzle_id}.json")) {
+            Ok(game_json) => {
+                Dir::Cache.write(&name, &game_json)?;
+                eprintln!("{progress}: downloaded");
+                downloaded += 1;
+            }
+            // later puzzles just haven't been published yet, which isn't worth giving up over
+            Err(Error::Http(e)) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                eprintln!("{progress}: not published yet");
+                missing += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    println!("downloaded {downloaded} puzzles ({cached} already cached, {missing} not found)");
+
+    Ok(())
+}
+
+/// Reads a puzzle's saved progress, if it has any. A save for a different board (if the puzzle has
+/// been changed since, say) doesn't count, and neither does one which can't be read, which would
+/// otherwise stop the puzzle being played at all.
+fn load_progress(save_name: &str, board: &Board) -> Option<ui::Progress> {
+    let json = match Dir::Data.read(save_name) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            eprintln!("warning: starting afresh, since {save_name} couldn't be read: {e}");
+            return None;
+        }
+    };
+    let solution = match serde_json::from_str::<Solution>(&json) {
+        Ok(solution) => solution,
+        Err(e) => {
+            eprintln!("warning: starting afresh, since {save_name} couldn't be parsed: {e}");
+            return None;
         }
     };
+    match solution.board() {
+        Ok(saved_board) if saved_board == *board => {}
+        Ok(_) => {
+            log::info!("ignoring {save_name}, which is for a different board");
+            return None;
+        }
+        Err(e) => {
+            eprintln!("warning: starting afresh, since {save_name} has a bad board: {e}");
+            return None;
+        }
+    }
+    log::info!("resuming from {save_name}");
+
+    Some(solution.progress(board))
+}
+
+/// Expands a leading `~` in a path given on the command line, and checks there's a file there.
+fn input_path(path: &Path) -> Result<PathBuf, Error> {
+    let expanded = storage::expand_home(path);
+    if !expanded.is_file() {
+        let message = format!("no such file: {}", path.display());
+        return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+    }
+
+    Ok(expanded)
+}
+
+fn render(solution: &Path, out: &Path) -> Result<(), Error> {
+    let solution = Solution::load(solution)?;
+    let board = solution.board()?;
+    let regions = solution.regions(&board)?;
 
-    let game = Game::<Color>::new(&board, &ruleset);
-    ui::run(game)?;
+    fs::write(out, svg::render(&board, &regions))?;
 
     Ok(())
 }
+
+fn shared(
+    code: &ShareCode,
+    out: Option<&Path>,
+    config: &ui::Config,
+    load_options: &LoadOptions,
+) -> Result<(), Error> {
+    let puzzle_id = code.puzzle_id.ok_or_else(|| {
+        Error::Validation("the share code doesn't say which puzzle it's for".to_owned())
+    })?;
+    let mut official_data = load_options.load(puzzle_id)?;
+    let board = official_data.board()?;
+    let ruleset = load_options.ruleset(&mut official_data, config)?;
+    let mut regions = code.regions(&board)?;
+
+    // a code can put any squares together, not just ones which could have been played
+    let topology = &*ruleset.topology;
+    if let Some(region) = regions
+        .iter()
+        .find(|region| !region.is_valid_shape(topology, &board))
+    {
+        let message = format!("bad share code: {} isn't in one piece", region.word(&board));
+        return Err(Error::Validation(message));
+    }
+
+    // in reading order of their first square, like --print-words
+    regions.sort_by_key(|region| region.iter_sorted().next());
+    for region in &regions {
+        println!("{}", region.word(&board));
+    }
+
+    if let Some(out) = out {
+        fs::write(out, svg::render(&board, &regions))?;
+    }
+
+    Ok(())
+}
+
+/// How a game went, for --result.
+#[derive(Serialize)]
+struct GameResult {
+    completed: bool,
+    score: usize,
+    seconds: f64,
+    regions: usize,
+}
+
+/// Prints a solution to a puzzle, the same one every time, returning whether there was one.
+fn print_solution(
+    puzzle: &str,
+    config: &ui::Config,
+    load_options: &LoadOptions,
+) -> Result<bool, Error> {
+    let mut official_data = load_options.load_id_or_file(puzzle)?;
+    let board = official_data.board()?;
+    let ruleset = load_options.ruleset(&mut official_data, config)?;
+    let game = config.new_game(&board, &ruleset)?;
+
+    match game.solve() {
+        Some(regions) => {
+            let solution = Solution::from_regions(puzzle.parse().ok(), &board, &regions);
+            println!("{}", serde_json::to_string(&solution)?);
+            Ok(true)
+        }
+        None => {
+            eprintln!("no solution");
+            Ok(false)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlacedWord {
+    word: String,
+    /// Seconds into the game.
+    placed_at: f64,
+}
+
+/// Exit code for a game which was left before the puzzle was completed, so scripts can tell it
+/// apart from an error.
+const INCOMPLETE: u8 = 2;
+
+fn try_main() -> Result<ExitCode, Error> {
+    logging::init()?;
+
+    let Paperbark {
+        mut config,
+        load_options,
+        print_words,
+        json,
+        timings,
+        result,
+        fresh,
+        save_solution,
+        share_code,
+        command,
+    } = Paperbark::from_args();
+    // where the puzzle comes from when it's given as files rather than by id
+    let mut files = None;
+    // the seed is picked here rather than left to the game, so it can be printed for playing the
+    // same way again
+    if config.seed.is_none() {
+        let seed = Rng::random_seed();
+        config.seed = Some(seed);
+        if config.is_random() {
+            eprintln!("random seed {seed} (play the same way again with --seed {seed})");
+        }
+    }
+    let puzzle_id = match command {
+        Command::Today => Some(puzzle_id_for_date(today())),
+        Command::Day { puzzle_id } => Some(puzzle_id as i64),
+        Command::Date { date } => Some(date_puzzle_id(date, today())?),
+        Command::Stdin => None,
+        Command::File { dictionary, game } => {
+            files = Some((dictionary, game));
+            None
+        }
+        Command::Id { date } => {
+            let puzzle_id = match date {
+                Some(date) => date_puzzle_id(date, today())?,
+                None => puzzle_id_for_date(today()),
+            };
+            println!("{puzzle_id} {}", date_for_puzzle_id(puzzle_id));
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Marathon { from, to } => {
+            run_marathon(from, to, config, &load_options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Autoplay { from, to, verify } => {
+            let all_solved = autoplay(from, to, verify, &config, &load_options)?;
+            return Ok(if all_solved {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+        Command::Prefetch { from, to } => {
+            prefetch(from, to, &load_options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Dump { puzzle } => {
+            dump(&puzzle, &load_options, json)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Info { puzzle } => {
+            info(&puzzle, &config, &load_options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Solve { puzzle } => {
+            let solved = print_solution(&puzzle, &config, &load_options)?;
+            return Ok(if solved {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+        Command::Diff { a, b } => {
+            diff(&a, &b, &load_options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Shared { code, out } => {
+            shared(&code, out.as_deref(), &config, &load_options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Render { solution, out } => {
+            render(&solution, &out)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+    let mut official_data = match (puzzle_id, files) {
+        (Some(puzzle_id), _) => load_options.load(puzzle_id)?,
+        (None, Some((dictionary, game))) => load_options.load_paths(&dictionary, &game)?,
+        (None, None) => load_options.load_stdin()?,
+    };
+
+    let board = official_data.board()?;
+    let ruleset = load_options.ruleset(&mut official_data, &config)?;
+
+    // custom puzzles have no id to keep track of their times by, and times are only a nicety, so
+    // ones which can't be read aren't worth stopping the game over (or overwriting)
+    let mut times = match puzzle_id.map(|_| SolveTimes::load()) {
+        Some(Ok(times)) => Some(times),
+        Some(Err(e)) => {
+            eprintln!("warning: not keeping solve times, since they couldn't be read: {e}");
+            None
+        }
+        None => None,
+    };
+    let mut puzzle = official_data.puzzle(puzzle_id);
+    puzzle.median_time = match (&times, puzzle_id) {
+        (Some(times), Some(puzzle_id)) => times.median(puzzle_id),
+        _ => None,
+    };
+
+    // puzzles are saved as they're left, so coming back to one later carries on from there
+    let save_name = puzzle_id.map(storage::save_name);
+    if let (Some(save_name), false) = (&save_name, fresh) {
+        puzzle.progress = load_progress(save_name, &board);
+    }
+    let game = config.new_game(&board, &ruleset)?;
+    // a puzzle finished last time has had its time counted already
+    let already_solved = puzzle
+        .progress
+        .as_ref()
+        .is_some_and(|progress| progress.covers(&game));
+
+    let outcome = ui::run(game, puzzle, config, None)?;
+    let game = outcome.game;
+    let completed = outcome.solved;
+
+    if let Some(save_name) = &save_name {
+        // losing the save is a shame, but not worth losing the rest of what's done after a game
+        let solution = Solution::from_game(puzzle_id, &game, outcome.elapsed);
+        if let Err(e) = Dir::Data.write(save_name, &serde_json::to_string_pretty(&solution)?) {
+            eprintln!("warning: couldn't save progress to {save_name}: {e}");
+        }
+    }
+
+    if let (Some(times), Some(puzzle_id), true, false) =
+        (&mut times, puzzle_id, completed, already_solved)
+    {
+        times.record(puzzle_id, outcome.elapsed);
+        if let Err(e) = times.save() {
+            eprintln!("warning: couldn't save solve times: {e}");
+        }
+    }
+
+    if result {
+        let result = GameResult {
+            completed,
+            score: game.score(),
+            seconds: outcome.elapsed.as_secs_f64(),
+            regions: game.regions().count(),
+        };
+        println!("{}", serde_json::to_string(&result)?);
+    }
+
+    if let Some(path) = save_solution {
+        Solution::from_game(puzzle_id, &game, outcome.elapsed).save(&path)?;
+    }
+
+    if share_code {
+        println!("{}", ShareCode::from_game(puzzle_id, &game).encode()?);
+    }
+
+    if print_words {
+        // list regions in reading order of their first square, so the output doesn't depend on
+        // the order they were committed in
+        let mut regions = game.regions().collect::<Vec<_>>();
+        regions.sort_by_key(|(region, _)| region.iter_sorted().next());
+
+        let words = regions
+            .into_iter()
+            .map(|(region, data)| PlacedWord {
+                word: region.word(game.board()),
+                placed_at: data.placed_at.as_secs_f64(),
+            })
+            .collect::<Vec<_>>();
+
+        match (json, timings) {
+            (true, true) => println!("{}", serde_json::to_string(&words)?),
+            (true, false) => {
+                let words = words.iter().map(|w| &w.word).collect::<Vec<_>>();
+                println!("{}", serde_json::to_string(&words)?);
+            }
+            (false, true) => {
+                for PlacedWord { word, placed_at } in words {
+                    let seconds = placed_at as u64;
+                    let (minutes, seconds) = (seconds / 60, seconds % 60);
+                    println!("{word} {minutes:02}:{seconds:02}");
+                }
+            }
+            (false, false) => {
+                for PlacedWord { word, .. } in words {
+                    println!("{word}");
+                }
+            }
+        }
+    }
+
+    if completed {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(INCOMPLETE))
+    }
+}
+
+fn main() -> ExitCode {
+    match try_main() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_JSON: &str = r#"{
+        "width": 3,
+        "height": 1,
+        "minSize": 3,
+        "maxSize": 3,
+        "regions": [[[0, 0], [1, 0], [2, 0]]],
+        "words": ["cat"]
+    }"#;
+
+    #[test]
+    fn puzzle_dates() {
+        assert_eq!(date_for_puzzle_id(1), epoch());
+        for id in [1, 2, 100, 365] {
+            assert_eq!(puzzle_id_for_date(date_for_puzzle_id(id)), id);
+        }
+    }
+
+    #[test]
+    fn today_puzzle() {
+        let today = NaiveDate::from_ymd(2022, 6, 1);
+        assert_eq!(date_puzzle_id(today, today).unwrap(), 27);
+        assert_eq!(date_puzzle_id(epoch(), today).unwrap(), 1);
+        assert!(date_puzzle_id(today.succ(), today).is_err());
+        assert!(date_puzzle_id(epoch().pred(), today).is_err());
+    }
+
+    #[test]
+    fn solve_time_median() {
+        let mut times = SolveTimes::default();
+        assert_eq!(times.median(1), None);
+
+        for seconds in [100, 300, 200] {
+            times.record(1, Duration::from_secs(seconds));
+        }
+        assert_eq!(times.median(1), Some(Duration::from_secs(200)));
+        assert_eq!(times.median(2), None);
+
+        // a slow attempt moves it halfway to the next time, not all the way to the slow one
+        times.record(1, Duration::from_secs(1000));
+        assert_eq!(times.median(1), Some(Duration::from_secs(250)));
+
+        let json = serde_json::to_string(&times).unwrap();
+        let times = serde_json::from_str::<SolveTimes>(&json).unwrap();
+        assert_eq!(times.median(1), Some(Duration::from_secs(250)));
+    }
+
+    #[test]
+    fn official_data_no_regions() {
+        let game_json = r#"{
+            "width": 0,
+            "height": 0,
+            "minSize": 3,
+            "maxSize": 3,
+            "regions": [],
+            "words": []
+        }"#;
+        assert!(matches!(
+            OfficialData::from_json("[]", game_json),
+            Err(Error::Validation(_))
+        ));
+
+        // a board with squares but no words to fill them is just as broken
+        let game_json = game_json.replace(r#""width": 0"#, r#""width": 3"#);
+        let game_json = game_json.replace(r#""height": 0"#, r#""height": 1"#);
+        assert!(matches!(
+            OfficialData::from_json("[]", &game_json),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn official_data_parse_errors() {
+        let message = |dictionary_json, game_json| {
+            let error = OfficialData::from_json(dictionary_json, game_json)
+                .err()
+                .unwrap();
+            error.to_string()
+        };
+
+        assert!(message("{", GAME_JSON).starts_with("failed to parse dictionary data: "));
+        assert!(message("[]", "{").starts_with("failed to parse game data: "));
+        let game_json = GAME_JSON.replace(r#""words": ["cat"]"#, r#""words": [3]"#);
+        assert!(message("[]", &game_json).starts_with("failed to parse game data: "));
+    }
+
+    #[test]
+    fn input_paths() {
+        let missing = input_path(Path::new("no/such/puzzle.json")).unwrap_err();
+        assert_eq!(missing.to_string(), "no such file: no/such/puzzle.json");
+        assert!(input_path(Path::new("Cargo.toml")).is_ok());
+        assert!(input_path(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("Cargo.toml")
+                .as_path()
+        )
+        .is_ok());
+
+        if let Some(home) = std::env::var_os("HOME") {
+            let expanded = storage::expand_home(Path::new("~/puzzles/1.json"));
+            assert_eq!(expanded, Path::new(&home).join("puzzles/1.json"));
+        }
+        assert_eq!(storage::expand_home(Path::new("a/~/b")), Path::new("a/~/b"));
+    }
+
+    #[test]
+    fn game_data_problems() {
+        let problems = |game_json| match GameData::parse(game_json) {
+            Err(Error::Validation(problems)) => problems,
+            _ => panic!("{game_json} should be invalid"),
+        };
+
+        let game_json = r#"{
+            "width": 3,
+            "height": 1,
+            "maxSize": 3,
+            "regions": [[[0, 0], [1, 0], [-2, 0]]],
+            "words": ["cat"]
+        }"#;
+        assert_eq!(
+            problems(game_json),
+            "missing minSize; region 1 has a square which isn't a pair of whole numbers: [-2,0]"
+        );
+
+        let game_json = r#"{
+            "width": 3,
+            "height": 1,
+            "minSize": 4,
+            "maxSize": 3,
+            "regions": [[[0, 0], [1, 0], [3, 0]], [[1, 0]]],
+            "words": ["cat"]
+        }"#;
+        assert_eq!(
+            problems(game_json),
+            "minSize 4 is more than maxSize 3; 2 regions but 1 words; \
+             cat has a letter outside the board at (3, 0); no word covers (2, 0)"
+        );
+
+        assert!(GameData::parse(GAME_JSON).is_ok());
+    }
+
+    #[test]
+    fn diff_puzzles_changes() {
+        let a = OfficialData::from_json("[]", GAME_JSON).unwrap();
+        assert!(diff_puzzles(&a, &a).unwrap().is_empty());
+
+        let b_json = r#"{
+            "width": 3,
+            "height": 2,
+            "minSize": 3,
+            "maxSize": 3,
+            "regions": [[[0, 0], [1, 0], [2, 0]], [[0, 1], [1, 1], [2, 1]]],
+            "words": ["cot", "dog"]
+        }"#;
+        let b = OfficialData::from_json("[]", b_json).unwrap();
+        assert_eq!(
+            diff_puzzles(&a, &b).unwrap(),
+            [
+                "size: 3x1 -> 3x2",
+                "(1, 0): A -> O",
+                "(0, 1): - -> D",
+                "(1, 1): - -> O",
+                "(2, 1): - -> G",
+                "~ CAT -> COT: (0, 0) (1, 0) (2, 0)",
+                "+ DOG: (0, 1) (1, 1) (2, 1)",
+            ]
+        );
+        assert_eq!(
+            diff_puzzles(&b, &a).unwrap()[6],
+            "- DOG: (0, 1) (1, 1) (2, 1)"
+        );
+    }
+
+    #[test]
+    fn official_data_join_words() {
+        let game_json = r#"{
+            "width": 4,
+            "height": 2,
+            "minSize": 8,
+            "maxSize": 8,
+            "regions": [[[0, 0], [1, 0], [2, 0], [3, 0], [0, 1], [1, 1], [2, 1], [3, 1]]],
+            "words": ["icecream"]
+        }"#;
+        let mut official_data =
+            OfficialData::from_json(r#"["ice-cream", "hot dog"]"#, game_json).unwrap();
+        let board = official_data.board().unwrap();
+
+        let region = board.squares().collect();
+        let ruleset = official_data.ruleset();
+        let game = Game::<()>::new(&board, &ruleset);
+        assert!(game.check_region(&region).is_err());
+
+        official_data.join_dictionary_words();
+        let ruleset = official_data.ruleset();
+        let game = Game::<()>::new(&board, &ruleset);
+        assert!(game.check_region(&region).is_ok());
+        assert!(ruleset.dictionary.contains("HOTDOG"));
+    }
+
+    #[test]
+    fn official_data_empty_dictionary() {
+        let official_data = OfficialData::from_json("[]", GAME_JSON).unwrap();
+        let board = official_data.board().unwrap();
+        let ruleset = official_data.ruleset();
+        assert_eq!(ruleset.dictionary.len(), 0);
+
+        let game = Game::<()>::new(&board, &ruleset);
+        assert!(!game.is_complete());
+
+        let region = board.squares().collect();
+        assert!(matches!(
+            game.check_region(&region),
+            Err(CheckRegionError::NotInDictionary)
+        ));
+    }
+}
@@ -0,0 +1,507 @@
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::game::*;
+use crate::ui::{self, Progress, RegionData, SavedRegion};
+use tui::style::Color;
+
+/// A board and the regions placed on it, complete or not, as written by `--save-solution` and
+/// when leaving a puzzle.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Solution {
+    pub puzzle_id: Option<i64>,
+    /// The board in [`Board::to_compact_string`] form.
+    pub board: String,
+    pub regions: Vec<SolvedRegion>,
+    /// Seconds into the game when it was saved.
+    #[serde(default)]
+    pub elapsed: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SolvedRegion {
+    pub squares: Vec<(usize, usize)>,
+    /// Seconds into the game.
+    #[serde(default)]
+    pub placed_at: f64,
+    /// Left out to have a color picked when the region is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<SavedColor>,
+    /// The word the region spells, for whoever reads the file. Loading reads it off the board
+    /// again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word: Option<String>,
+}
+
+/// A region's color, saved by name, as a number for one of the 256 indexed colors, or as #rrggbb.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavedColor(pub Color);
+
+impl Serialize for SavedColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&ui::color_name(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for SavedColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ui::parse_color(&name)
+            .map(SavedColor)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Solution {
+    pub fn from_game(puzzle_id: Option<i64>, game: &Game<RegionData>, elapsed: Duration) -> Self {
+        let regions = game
+            .regions()
+            .map(|(region, data)| SolvedRegion {
+                squares: region.iter_sorted().map(|s| (s.x, s.y)).collect(),
+                placed_at: data.placed_at.as_secs_f64(),
+                color: Some(SavedColor(data.color)),
+                word: Some(region.word(game.board())),
+            })
+            .collect();
+
+        Self {
+            puzzle_id,
+            board: game.board().to_compact_string(),
+            regions,
+            elapsed: elapsed.as_secs_f64(),
+        }
+    }
+
+    /// A solution made up of `regions` on `board`, without any timings or colors, such as one
+    /// found by [`Game::solve`].
+    pub fn from_regions(puzzle_id: Option<i64>, board: &Board, regions: &[Region]) -> Self {
+        let regions = regions
+            .iter()
+            .map(|region| SolvedRegion {
+                squares: region.iter_sorted().map(|s| (s.x, s.y)).collect(),
+                placed_at: 0.0,
+                color: None,
+                word: Some(region.word(board)),
+            })
+            .collect();
+
+        Self {
+            puzzle_id,
+            board: board.to_compact_string(),
+            regions,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let json = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn board(&self) -> Result<Board, Error> {
+        Board::from_compact_string(&self.board)
+    }
+
+    /// The regions and timings, to pick the game up where it was left off on `board`. Regions
+    /// which don't fit on the board are left out and counted, rather than losing the whole game
+    /// to one of them.
+    pub fn progress(&self, board: &Board) -> Progress {
+        let seconds = |seconds: f64| Duration::try_from_secs_f64(seconds).unwrap_or_default();
+        let mut used = HashSet::new();
+        let mut dropped = 0;
+        let regions = self
+            .regions
+            .iter()
+            .filter_map(|solved| match solved.region(board, &mut used) {
+                Ok(region) => Some(SavedRegion {
+                    region,
+                    placed_at: seconds(solved.placed_at),
+                    color: solved.color.map(|SavedColor(color)| color),
+                }),
+                Err(e) => {
+                    log::warn!("leaving out a saved region: {e}");
+                    dropped += 1;
+                    None
+                }
+            })
+            .collect();
+
+        Progress {
+            regions,
+            elapsed: seconds(self.elapsed),
+            dropped,
+        }
+    }
+
+    /// Checks the regions fit on the board without overlapping, and returns them.
+    pub fn regions(&self, board: &Board) -> Result<Vec<Region>, Error> {
+        let mut used = HashSet::new();
+
+        self.regions
+            .iter()
+            .map(|region| region.region(board, &mut used))
+            .collect()
+    }
+}
+
+impl SolvedRegion {
+    /// Checks the region fits on the board without covering any of the `used` squares, which it
+    /// then adds its own squares to.
+    fn region(&self, board: &Board, used: &mut HashSet<(usize, usize)>) -> Result<Region, Error> {
+        for &(x, y) in &self.squares {
+            if x >= board.width() || y >= board.height() {
+                let message = format!("square ({x}, {y}) is off the board");
+                return Err(Error::Validation(message));
+            }
+            if used.contains(&(x, y)) {
+                let message = format!("square ({x}, {y}) is in more than one region");
+                return Err(Error::Validation(message));
+            }
+        }
+        used.extend(self.squares.iter().copied());
+
+        Ok(self.squares.iter().map(|&s| s.into()).collect())
+    }
+}
+
+/// The characters each square of a share code can be, one for each region (and the first for
+/// squares no region covers), safe to paste into a url.
+const SHARE_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A short form of a solution to send to someone who can load the same puzzle, written
+/// `ID.WIDTHxHEIGHT.SQUARES` with a character for each square of the board in reading order
+/// saying which region it's in.
+#[derive(Debug, PartialEq)]
+pub struct ShareCode {
+    pub puzzle_id: Option<i64>,
+    pub width: usize,
+    pub height: usize,
+    pub regions: Vec<Region>,
+}
+
+impl ShareCode {
+    pub fn from_game<D>(puzzle_id: Option<i64>, game: &Game<D>) -> Self {
+        let board = game.board();
+
+        Self {
+            puzzle_id,
+            width: board.width(),
+            height: board.height(),
+            regions: game.regions().map(|(region, _)| region.clone()).collect(),
+        }
+    }
+
+    /// Writes the code, which can only tell apart as many regions as there are characters to
+    /// mark them with.
+    pub fn encode(&self) -> Result<String, Error> {
+        let max_regions = SHARE_ALPHABET.len() - 1;
+        if self.regions.len() > max_regions {
+            let message = format!("share codes can only hold {max_regions} regions");
+            return Err(Error::Validation(message));
+        }
+
+        // number the regions by where they start, so the same solution always gets the same code
+        let mut regions = self.regions.iter().collect::<Vec<_>>();
+        regions.sort_by_key(|region| region.iter_sorted().next());
+
+        let mut squares = vec![SHARE_ALPHABET[0]; self.width * self.height];
+        for (i, region) in regions.into_iter().enumerate() {
+            for Square { x, y } in region.squares() {
+                if x >= self.width || y >= self.height {
+                    let message = format!("square ({x}, {y}) is off the board");
+                    return Err(Error::Validation(message));
+                }
+                squares[y * self.width + x] = SHARE_ALPHABET[i + 1];
+            }
+        }
+
+        let puzzle_id = self.puzzle_id.map(|id| id.to_string()).unwrap_or_default();
+        let squares = String::from_utf8_lossy(&squares);
+        Ok(format!(
+            "{puzzle_id}.{}x{}.{squares}",
+            self.width, self.height
+        ))
+    }
+
+    /// The regions, as long as the code was made for a board the size of `board`.
+    pub fn regions(&self, board: &Board) -> Result<Vec<Region>, Error> {
+        if (self.width, self.height) != (board.width(), board.height()) {
+            let message = format!(
+                "share code is for a {}x{} board, not {}x{}",
+                self.width,
+                self.height,
+                board.width(),
+                board.height()
+            );
+            return Err(Error::Validation(message));
+        }
+
+        Ok(self.regions.clone())
+    }
+}
+
+impl FromStr for ShareCode {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let invalid = |problem: String| Error::Validation(format!("bad share code: {problem}"));
+        let malformed = || invalid("it should look like ID.WIDTHxHEIGHT.SQUARES".to_owned());
+
+        let mut parts = code.trim().split('.');
+        let (puzzle_id, size, squares) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(puzzle_id), Some(size), Some(squares)) if parts.next().is_none() => {
+                (puzzle_id, size, squares)
+            }
+            _ => return Err(malformed()),
+        };
+
+        let puzzle_id = match puzzle_id {
+            "" => None,
+            id => Some(id.parse::<i64>().map_err(|_| malformed())?),
+        };
+        let (width, height) = size
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<usize>().ok()?, h.parse::<usize>().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .ok_or_else(malformed)?;
+
+        // a size too big to multiply out can't be a real board
+        let size = width.checked_mul(height).ok_or_else(malformed)?;
+        let count = squares.chars().count();
+        if count != size {
+            let problem =
+                format!("it has {count} squares, but a {width}x{height} board has {size}");
+            return Err(invalid(problem));
+        }
+
+        let mut regions = Vec::<Region>::new();
+        for (i, c) in squares.chars().enumerate() {
+            let index = SHARE_ALPHABET
+                .iter()
+                .position(|&a| a as char == c)
+                .ok_or_else(|| invalid(format!("'{c}' doesn't stand for a region")))?;
+            if index == 0 {
+                continue;
+            }
+
+            if regions.len() < index {
+                regions.resize_with(index, Region::new);
+            }
+            regions[index - 1].add_square((i % width, i / width).into());
+        }
+        if let Some(i) = regions.iter().position(|region| region.size() == 0) {
+            return Err(invalid(format!("region {} is missing", i + 1)));
+        }
+
+        Ok(Self {
+            puzzle_id,
+            width,
+            height,
+            regions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solution_round_trip() {
+        let board = Board::new(3, "CATDOG").unwrap();
+        let ruleset = Ruleset::new(
+            3,
+            3,
+            ["CAT", "DOG"].into_iter().map(|w| w.to_owned()).collect(),
+        );
+        let mut game = Game::new(&board, &ruleset);
+        let region = Region::from_squares([(0, 1).into(), (1, 1).into(), (2, 1).into()]);
+        let data = RegionData {
+            color: tui::style::Color::Red,
+            placed_at: Duration::from_millis(1500),
+        };
+        game.add_region(game.check_region(&region).unwrap(), data);
+
+        let solution = Solution::from_game(Some(7), &game, Duration::from_secs(20));
+        let json = serde_json::to_string(&solution).unwrap();
+        let solution = serde_json::from_str::<Solution>(&json).unwrap();
+
+        assert_eq!(solution.puzzle_id, Some(7));
+        assert_eq!(solution.regions[0].placed_at, 1.5);
+        assert!(solution.board().unwrap() == board);
+        assert_eq!(
+            solution.regions(&board).unwrap(),
+            std::slice::from_ref(&region)
+        );
+
+        let progress = solution.progress(&board);
+        assert_eq!(
+            progress.regions,
+            [SavedRegion {
+                region,
+                placed_at: Duration::from_millis(1500),
+                color: Some(Color::Red),
+            }]
+        );
+        assert_eq!(progress.elapsed, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn solution_colors() {
+        let colors = [
+            Color::Red,
+            Color::LightCyan,
+            Color::Indexed(17),
+            Color::Rgb(255, 128, 0),
+        ];
+        for color in colors {
+            let json = serde_json::to_string(&SavedColor(color)).unwrap();
+            assert_eq!(serde_json::from_str::<SavedColor>(&json).unwrap().0, color);
+        }
+        assert_eq!(
+            serde_json::to_string(&SavedColor(Color::Rgb(255, 128, 0))).unwrap(),
+            r##""#ff8000""##
+        );
+        assert!(serde_json::from_str::<SavedColor>(r#""mauve""#).is_err());
+
+        // solutions from before colors were saved still load, and get colors picked for them
+        let json = r#"{"puzzle_id": 1, "board": "3x1:CAT", "regions": [{"squares": [[0, 0], [1, 0], [2, 0]]}]}"#;
+        let solution = serde_json::from_str::<Solution>(json).unwrap();
+        assert_eq!(solution.regions[0].color, None);
+        let board = solution.board().unwrap();
+        assert_eq!(solution.progress(&board).regions[0].color, None);
+    }
+
+    #[test]
+    fn solution_invalid_regions() {
+        let board = Board::new(3, "CATDOG").unwrap();
+        let solution = |squares: Vec<(usize, usize)>| Solution {
+            puzzle_id: Some(1),
+            board: board.to_compact_string(),
+            regions: vec![
+                SolvedRegion {
+                    squares: vec![(0, 0)],
+                    placed_at: 0.0,
+                    color: None,
+                    word: None,
+                },
+                SolvedRegion {
+                    squares,
+                    placed_at: 0.0,
+                    color: None,
+                    word: None,
+                },
+            ],
+            elapsed: 0.0,
+        };
+
+        assert!(solution(vec![(1, 0)]).regions(&board).is_ok());
+        assert!(solution(vec![(3, 0)]).regions(&board).is_err());
+        assert!(solution(vec![(0, 0)]).regions(&board).is_err());
+
+        // picking a game back up keeps what it can
+        for squares in [vec![(3, 0)], vec![(0, 0), (1, 0)]] {
+            let progress = solution(squares).progress(&board);
+            assert_eq!(progress.regions.len(), 1);
+            assert_eq!(progress.regions[0].region.size(), 1);
+            assert_eq!(progress.dropped, 1);
+        }
+    }
+
+    #[test]
+    fn solution_from_regions() {
+        let board = Board::new(3, "CATDOG").unwrap();
+        let ruleset = Ruleset::new(
+            3,
+            3,
+            ["CAT", "DOG"].into_iter().map(|w| w.to_owned()).collect(),
+        );
+        let regions = Game::<()>::new(&board, &ruleset).solve().unwrap();
+
+        let solution = Solution::from_regions(Some(42), &board, &regions);
+        assert_eq!(
+            serde_json::to_string(&solution).unwrap(),
+            concat!(
+                r#"{"puzzle_id":42,"board":"3x2:CATDOG","regions":["#,
+                r#"{"squares":[[0,0],[1,0],[2,0]],"placed_at":0.0,"word":"CAT"},"#,
+                r#"{"squares":[[0,1],[1,1],[2,1]],"placed_at":0.0,"word":"DOG"}"#,
+                r#"],"elapsed":0.0}"#,
+            )
+        );
+
+        // it can be loaded back like any other
+        assert_eq!(solution.regions(&board).unwrap(), regions);
+    }
+
+    #[test]
+    fn share_code_round_trip() {
+        let board = Board::new(3, "CATDOG").unwrap();
+        let ruleset = Ruleset::new(
+            3,
+            3,
+            ["CAT", "DOG"].into_iter().map(|w| w.to_owned()).collect(),
+        );
+        let mut game = Game::<()>::new(&board, &ruleset);
+        let dog = Region::from_squares([(0, 1).into(), (1, 1).into(), (2, 1).into()]);
+        game.add_region(game.check_region(&dog).unwrap(), ());
+
+        let code = ShareCode::from_game(Some(42), &game).encode().unwrap();
+        assert_eq!(code, "42.3x2.AAABBB");
+        let decoded = code.parse::<ShareCode>().unwrap();
+        assert_eq!(decoded.puzzle_id, Some(42));
+        assert_eq!(decoded.regions(&board).unwrap(), std::slice::from_ref(&dog));
+
+        // regions are numbered by where they start, not when they were added
+        let cat = Region::from_squares([(0, 0).into(), (1, 0).into(), (2, 0).into()]);
+        game.add_region(game.check_region(&cat).unwrap(), ());
+        let code = ShareCode::from_game(None, &game).encode().unwrap();
+        assert_eq!(code, ".3x2.BBBCCC");
+        assert_eq!(code.parse::<ShareCode>().unwrap().regions, [cat, dog]);
+
+        // a code only fits a board of the same size
+        let other = Board::new(2, "CATDOG").unwrap();
+        assert!(decoded.regions(&other).is_err());
+    }
+
+    #[test]
+    fn share_code_malformed() {
+        for code in [
+            "",
+            "42",
+            "42.3x2",
+            "42.3x2.AAABBB.X",
+            "x.3x2.AAABBB",
+            "42.3by2.AAABBB",
+            "42.0x2.",
+            "42.3x2.AAABB",
+            "42.3x2.AAAB!B",
+            // there's no region B for C to follow
+            "42.3x2.AAACCC",
+            // too big to have any number of squares
+            "1.4294967296x4294967296.A",
+            "1.18446744073709551615x2.A",
+        ] {
+            assert!(code.parse::<ShareCode>().is_err(), "{code}");
+        }
+
+        let error = "1.3x2.AAAB".parse::<ShareCode>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid puzzle: bad share code: it has 4 squares, but a 3x2 board has 6"
+        );
+    }
+}
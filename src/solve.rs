@@ -0,0 +1,85 @@
+use std::ops::ControlFlow;
+
+use crate::game::*;
+
+/// Counts the distinct ways of covering the whole board with words, stopping once `limit` have
+/// been found. Asking for at most 2 is enough to tell whether a puzzle's solution is unique.
+pub fn count_solutions(board: &Board, ruleset: &Ruleset, limit: usize) -> usize {
+    let mut count = 0;
+    if limit > 0 {
+        let mut game = Game::new(board, ruleset);
+        // backtracking undoes everything anyway
+        game.set_history_limit(0);
+        let mut budget = usize::MAX;
+        let _ = game.search_completions(&mut budget, &mut |_| {
+            count += 1;
+            if count < limit {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+    }
+
+    count
+}
+
+/// Finds a way of covering the whole board with words, if there is one.
+pub fn find_solution(board: &Board, ruleset: &Ruleset) -> Option<Vec<Region>> {
+    Game::<()>::new(board, ruleset).solve()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Board {
+        #[rustfmt::skip]
+        let board = Board::new(
+            3,
+            concat!(
+                "ABC",
+                "DEF",
+                "GHI",
+            )
+        )
+        .unwrap();
+
+        board
+    }
+
+    fn ruleset_with(words: &[&str]) -> Ruleset {
+        let dictionary = words.iter().map(|&w| w.to_owned()).collect();
+
+        Ruleset::new(3, 3, dictionary)
+    }
+
+    #[test]
+    fn count_solutions() {
+        let board = board();
+
+        let ruleset = ruleset_with(&["ABC", "DEF", "GHI"]);
+        assert_eq!(super::count_solutions(&board, &ruleset, 2), 1);
+
+        // rows or columns
+        let ruleset = ruleset_with(&["ABC", "DEF", "GHI", "ADG", "BEH", "CFI"]);
+        assert_eq!(super::count_solutions(&board, &ruleset, 2), 2);
+        assert_eq!(super::count_solutions(&board, &ruleset, 1), 1);
+        assert_eq!(super::count_solutions(&board, &ruleset, 0), 0);
+
+        let ruleset = ruleset_with(&["ABC", "DEF"]);
+        assert_eq!(super::count_solutions(&board, &ruleset, 2), 0);
+    }
+
+    #[test]
+    fn find_solution() {
+        let board = board();
+        let ruleset = ruleset_with(&["ABC", "DEF", "GHI", "ADG"]);
+        let regions = super::find_solution(&board, &ruleset).unwrap();
+        let words = regions.iter().map(|region| region.word(&board));
+        assert_eq!(words.collect::<Vec<_>>(), ["ABC", "DEF", "GHI"]);
+
+        let ruleset = ruleset_with(&["ABC", "DEF"]);
+        assert_eq!(super::find_solution(&board, &ruleset), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Copy, Clone)]
+pub enum Dir {
+    /// Things we can download again, like puzzles and the dictionary
+    Cache,
+    /// Things only the player has, like results
+    Data,
+}
+
+impl Dir {
+    fn path(self) -> Option<PathBuf> {
+        let (var, fallback) = match self {
+            Dir::Cache => ("XDG_CACHE_HOME", ".cache"),
+            Dir::Data => ("XDG_DATA_HOME", ".local/share"),
+        };
+
+        let dir = match env::var_os(var) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(fallback),
+        };
+
+        Some(dir.join("paperbark"))
+    }
+
+    fn file(self, name: &str) -> io::Result<PathBuf> {
+        let dir = self.path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "couldn't find a home directory")
+        })?;
+
+        Ok(dir.join(name))
+    }
+
+    pub fn read(self, name: &str) -> io::Result<String> {
+        fs::read_to_string(self.file(name)?)
+    }
+
+    /// How long ago a file was last written, if it's there.
+    pub fn age(self, name: &str) -> Option<Duration> {
+        let modified = fs::metadata(self.file(name).ok()?).ok()?.modified().ok()?;
+
+        modified.elapsed().ok()
+    }
+
+    pub fn contains(self, name: &str) -> bool {
+        self.file(name).is_ok_and(|path| path.is_file())
+    }
+
+    pub fn write(self, name: &str, contents: &str) -> io::Result<()> {
+        let path = self.writable_path(name)?;
+        // write alongside and move it into place, so stopping part way through leaves the old file
+        // as it was rather than half written
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, &path)
+    }
+
+    /// Where a file lives, creating the directories it goes in, for when it's too big to pass
+    /// around as a string.
+    pub fn writable_path(self, name: &str) -> io::Result<PathBuf> {
+        let path = self.file(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(path)
+    }
+}
+
+/// Expands a `~` at the start of `path` to the home directory, for paths the shell didn't expand
+/// (because they were quoted, say). Other paths, relative or absolute, are left as they are.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_owned(),
+    }
+}
+
+pub fn dictionary_name() -> String {
+    "words.json".to_owned()
+}
+
+pub fn sorted_dictionary_name() -> String {
+    "words.sorted.txt".to_owned()
+}
+
+pub fn dictionary_validators_name() -> String {
+    "words.validators.json".to_owned()
+}
+
+pub fn puzzle_name(puzzle_id: i64) -> String {
+    format!("puzzles/{puzzle_id}.json")
+}
+
+pub fn save_name(puzzle_id: i64) -> String {
+    format!("saves/{puzzle_id}.json")
+}
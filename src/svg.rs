@@ -0,0 +1,117 @@
+use std::fmt::Write;
+
+use crate::game::*;
+
+const CELL_SIZE: usize = 48;
+const MARGIN: usize = 8;
+
+// the same hues as the terminal palette, but light enough for black letters to read on top
+const COLORS: &[&str] = &[
+    "#f4a6a6", "#a6e3a6", "#f4e3a0", "#a6c4f4", "#e3a6e3", "#a6e3e3",
+];
+
+/// Draws the board as an SVG image, filling each region with a color and outlining it.
+pub fn render(board: &Board, regions: &[Region]) -> String {
+    let width = board.width() * CELL_SIZE + 2 * MARGIN;
+    let height = board.height() * CELL_SIZE + 2 * MARGIN;
+    let region_of = |square: Square| regions.iter().position(|r| r.contains(square));
+
+    let mut svg = String::new();
+    // writing to a string can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{width}" height="{height}" fill="white"/>"#
+    );
+
+    for square in board.squares() {
+        let (x, y) = corner(square);
+        let fill = match region_of(square) {
+            Some(i) => COLORS[i % COLORS.len()],
+            None => "white",
+        };
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{x}" y="{y}" width="{CELL_SIZE}" height="{CELL_SIZE}" fill="{fill}" stroke="#cccccc"/>"##
+        );
+
+        let (cx, cy) = (x + CELL_SIZE / 2, y + CELL_SIZE / 2);
+        let letter = escape(board.letter(square));
+        let _ = writeln!(
+            svg,
+            r#"<text x="{cx}" y="{cy}" font-family="monospace" font-size="{}" text-anchor="middle" dominant-baseline="central">{letter}</text>"#,
+            CELL_SIZE / 2
+        );
+    }
+
+    // outline each region by drawing every edge which doesn't lead to a square in the same region
+    for square in board.squares() {
+        let region = match region_of(square) {
+            Some(i) => i,
+            None => continue,
+        };
+        let (x, y) = corner(square);
+        let (x2, y2) = (x + CELL_SIZE, y + CELL_SIZE);
+
+        let outside = |dx: isize, dy: isize| {
+            let nx = square.x as isize + dx;
+            let ny = square.y as isize + dy;
+            if nx < 0 || ny < 0 {
+                return true;
+            }
+            region_of((nx as usize, ny as usize).into()) != Some(region)
+        };
+        let edges = [
+            (outside(0, -1), (x, y, x2, y)),
+            (outside(0, 1), (x, y2, x2, y2)),
+            (outside(-1, 0), (x, y, x, y2)),
+            (outside(1, 0), (x2, y, x2, y2)),
+        ];
+        for (_, (x1, y1, x2, y2)) in edges.into_iter().filter(|(outside, _)| *outside) {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="black" stroke-width="3" stroke-linecap="square"/>"#
+            );
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn corner(square: Square) -> (usize, usize) {
+    (MARGIN + square.x * CELL_SIZE, MARGIN + square.y * CELL_SIZE)
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '<' => "&lt;".to_owned(),
+        '>' => "&gt;".to_owned(),
+        '&' => "&amp;".to_owned(),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_outlines_regions() {
+        let board = Board::new(3, "CATDO&").unwrap();
+        let top = Region::from_squares([(0, 0).into(), (1, 0).into(), (2, 0).into()]);
+
+        let svg = render(&board, &[top]);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<text").count(), 6);
+        assert!(svg.contains(">&amp;</text>"));
+        assert_eq!(svg.matches(COLORS[0]).count(), 3);
+
+        // a straight line of three has eight outside edges: three above, three below and two ends
+        assert_eq!(svg.matches("<line").count(), 8);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::game::{Board, Region, Square};
+
+/// Which squares of a board are next to each other, and so can be part of the same region.
+pub trait Topology {
+    /// The squares next to `square`, which must be on `board`.
+    fn neighbours(&self, square: Square, board: &Board) -> Vec<Square>;
+
+    fn is_neighbour(&self, a: Square, b: Square, board: &Board) -> bool {
+        self.neighbours(a, board).contains(&b)
+    }
+
+    /// Whether the squares of `region` all join up into one piece.
+    fn is_contiguous(&self, region: &Region, board: &Board) -> bool {
+        region.is_contiguous(self, board)
+    }
+
+    /// The number of separate pieces the squares of `region` make up.
+    fn piece_count(&self, region: &Region, board: &Board) -> usize {
+        region.piece_count(self, board)
+    }
+}
+
+/// The plain grid, where a square is next to the squares above, below and either side of it.
+pub struct Orthogonal;
+
+impl Topology for Orthogonal {
+    fn neighbours(&self, square: Square, board: &Board) -> Vec<Square> {
+        board.neighbours(square).collect()
+    }
+
+    fn is_neighbour(&self, a: Square, b: Square, _board: &Board) -> bool {
+        a.is_neighbour_of(b)
+    }
+
+    fn is_contiguous(&self, region: &Region, _board: &Board) -> bool {
+        // regions keep track of this themselves for the plain grid
+        region.is_connected()
+    }
+
+    fn piece_count(&self, region: &Region, _board: &Board) -> usize {
+        region.component_count()
+    }
+}
+
+/// A grid which wraps around at its edges, so the leftmost column is next to the rightmost and
+/// the top row is next to the bottom.
+pub struct Toroidal;
+
+impl Topology for Toroidal {
+    fn neighbours(&self, square: Square, board: &Board) -> Vec<Square> {
+        let (width, height) = (board.width(), board.height());
+        let Square { x, y } = square;
+
+        let mut neighbours = vec![
+            Square {
+                x: (x + width - 1) % width,
+                y,
+            },
+            Square {
+                x: (x + 1) % width,
+                y,
+            },
+            Square {
+                x,
+                y: (y + height - 1) % height,
+            },
+            Square {
+                x,
+                y: (y + 1) % height,
+            },
+        ];
+        // on a board only one or two squares across, wrapping around leads back to the same
+        // squares
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours.retain(|&s| s != square);
+
+        neighbours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contiguity() {
+        let board = Board::new(3, "ABCDEFGHI").unwrap();
+        let region =
+            |squares: &[(usize, usize)]| Region::from_squares(squares.iter().map(|&s| s.into()));
+
+        let row = region(&[(0, 1), (1, 1), (2, 1)]);
+        assert!(Orthogonal.is_contiguous(&row, &board));
+        assert!(Toroidal.is_contiguous(&row, &board));
+
+        // only joined up around the sides, or the top and bottom
+        for squares in [&[(0, 1), (2, 1)], &[(1, 0), (1, 2)]] {
+            let wrapped = region(squares);
+            assert!(!Orthogonal.is_contiguous(&wrapped, &board));
+            assert!(Toroidal.is_contiguous(&wrapped, &board));
+        }
+
+        // corners are still only next to each other by wrapping one way at a time
+        let corners = region(&[(0, 0), (2, 2)]);
+        assert!(!Orthogonal.is_contiguous(&corners, &board));
+        assert!(!Toroidal.is_contiguous(&corners, &board));
+    }
+
+    #[test]
+    fn toroidal_neighbours() {
+        let board = Board::new(3, "ABCDEFGHI").unwrap();
+        let mut neighbours = Toroidal.neighbours((0, 0).into(), &board);
+        neighbours.sort_unstable();
+        assert_eq!(
+            neighbours,
+            [(1, 0).into(), (2, 0).into(), (0, 1).into(), (0, 2).into()]
+        );
+
+        // wrapping around a row of two squares only finds the other one
+        let board = Board::new(2, "AB").unwrap();
+        assert_eq!(Toroidal.neighbours((0, 0).into(), &board), [(1, 0).into()]);
+    }
+}
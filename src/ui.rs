@@ -1,32 +1,842 @@
-use crossterm::event::{self, Event, KeyCode};
+use chrono::NaiveDate;
+use crossterm::cursor::MoveTo;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
 };
 use itertools::{chain, iproduct};
-use std::collections::HashMap;
-use std::io;
-use std::time::Duration;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
 use tui::backend::Backend;
 use tui::backend::CrosstermBackend;
 use tui::buffer::Buffer;
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Paragraph, Widget};
+use tui::widgets::{Block, Borders, Clear, Paragraph, Widget};
 use tui::Frame;
 use tui::Terminal;
 
+use crate::error::Error;
 use crate::game::*;
+use crate::rng::Rng;
+use crate::topology::Toroidal;
+
+#[derive(Copy, Clone, Debug)]
+pub enum StatusBarPosition {
+    Top,
+    Bottom,
+}
+
+impl FromStr for StatusBarPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            _ => Err(format!("unknown status bar position \"{s}\"")),
+        }
+    }
+}
+
+/// How the letters of a selection are read to make a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordOrder {
+    /// Top to bottom, then left to right, no matter how the squares were selected
+    ReadingOrder,
+    /// In the order the squares were selected
+    SelectionOrder,
+}
+
+impl FromStr for WordOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reading" => Ok(Self::ReadingOrder),
+            "selection" => Ok(Self::SelectionOrder),
+            _ => Err(format!("unknown word order \"{s}\"")),
+        }
+    }
+}
+
+/// Where the cursor is when a puzzle starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorStart {
+    Origin,
+    Center,
+    /// Any square which isn't already taken
+    Random,
+}
+
+impl FromStr for CursorStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "origin" => Ok(Self::Origin),
+            "center" => Ok(Self::Center),
+            "random" => Ok(Self::Random),
+            _ => Err(format!("unknown cursor start \"{s}\"")),
+        }
+    }
+}
+
+impl CursorStart {
+    fn square<D>(self, game: &Game<D>, rng: &mut Rng) -> Square {
+        match self {
+            Self::Origin => nearest_free_square(game, (0, 0).into()),
+            Self::Center => nearest_free_square(game, center_square(game.board())),
+            Self::Random => {
+                let free = game
+                    .board()
+                    .squares()
+                    .filter(|&s| game.is_square_free(s))
+                    .collect::<Vec<_>>();
+                match rng.choose(&free) {
+                    Some(&square) => square,
+                    None => nearest_free_square(game, (0, 0).into()),
+                }
+            }
+        }
+    }
+}
+
+/// The free square fewest steps away from `from`, preferring squares higher up and then further
+/// left when there's a tie. On a full board, it's the nearest playable square instead, which is
+/// `from` itself unless it's outside the play area.
+fn nearest_free_square<D>(game: &Game<D>, from: Square) -> Square {
+    let distance = |s: &Square| (s.x.abs_diff(from.x) + s.y.abs_diff(from.y), s.y, s.x);
+    let nearest = |is_candidate: &dyn Fn(Square) -> bool| {
+        game.board()
+            .squares()
+            .filter(|&s| is_candidate(s))
+            .min_by_key(distance)
+    };
+
+    nearest(&|s| game.is_square_free(s))
+        .or_else(|| nearest(&|s| game.is_playable(s)))
+        .unwrap_or(from)
+}
+
+/// The middle square of the board, or the one up and to the left of the middle when there's an
+/// even number of rows or columns.
+fn center_square(board: &Board) -> Square {
+    ((board.width() - 1) / 2, (board.height() - 1) / 2).into()
+}
+
+/// A rectangle of the board to play on, leaving the rest of it out of the game. It's written
+/// `WIDTHxHEIGHT` for one in the top left corner, or `WIDTHxHEIGHT+X+Y` to start it elsewhere.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayArea {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl FromStr for PlayArea {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid play area \"{s}\"");
+        let number = |n: &str| n.parse::<usize>().map_err(|_| invalid());
+
+        let mut parts = s.split('+');
+        let (width, height) = parts
+            .next()
+            .and_then(|size| size.split_once('x'))
+            .ok_or_else(invalid)?;
+        let (width, height) = (number(width)?, number(height)?);
+        let (x, y) = match (parts.next(), parts.next(), parts.next()) {
+            (None, _, _) => (0, 0),
+            (Some(x), Some(y), None) => (number(x)?, number(y)?),
+            _ => return Err(invalid()),
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+impl PlayArea {
+    /// The squares of the area, which has to fit on `board`.
+    fn region(&self, board: &Board) -> Result<Region, Error> {
+        let PlayArea {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+        if x + width > board.width() || y + height > board.height() {
+            let message = format!(
+                "a {width}x{height} play area at ({x}, {y}) doesn't fit on a {}x{} board",
+                board.width(),
+                board.height()
+            );
+            return Err(Error::Validation(message));
+        }
+
+        Ok(iproduct!(x..x + width, y..y + height)
+            .map(|s| s.into())
+            .collect())
+    }
+}
+
+/// Colors which suit the terminal's background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+}
+
+impl FromStr for ThemePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            _ => Err(format!("unknown theme \"{s}\"")),
+        }
+    }
+}
+
+/// A background color, or none to leave the terminal's own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Background(Option<Color>);
+
+impl FromStr for Background {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self(None)),
+            _ => Ok(Self(Some(parse_color(s)?))),
+        }
+    }
+}
+
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+/// Reads a color written by [`color_name`]: a name, a number for one of the 256 indexed colors,
+/// or #rrggbb.
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some((_, color)) = COLOR_NAMES.iter().find(|(name, _)| *name == s) {
+        return Ok(*color);
+    }
+
+    let rgb = s
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+    if let Some(rgb) = rgb {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        return Ok(Color::Rgb(r, g, b));
+    }
+
+    match s.parse::<u8>() {
+        Ok(index) => Ok(Color::Indexed(index)),
+        Err(_) => Err(format!("unknown color \"{s}\"")),
+    }
+}
+
+/// Names a color so [`parse_color`] can read it back.
+pub fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::Indexed(index) => index.to_string(),
+        color => {
+            let name = COLOR_NAMES.iter().find(|(_, c)| *c == color);
+            name.map_or("reset", |(name, _)| name).to_owned()
+        }
+    }
+}
+
+#[derive(StructOpt, Clone)]
+pub struct Config {
+    /// Where to draw the status bar (top or bottom)
+    #[structopt(long, default_value = "bottom", global = true)]
+    pub status_position: StatusBarPosition,
+    /// Height of the status bar in lines [default: 1, or 4 with --verbose-status]
+    #[structopt(long, global = true)]
+    pub status_height: Option<u16>,
+    /// Show the region count, score and timer in the status bar
+    #[structopt(long, global = true)]
+    pub verbose_status: bool,
+    /// How many placements to try after each change looking for a way to finish the board, before
+    /// assuming there is one (0 to not look)
+    #[structopt(long, global = true, default_value = "10000")]
+    pub completion_budget: usize,
+    /// Ask before removing a region worth at least this many points (0 to always ask)
+    #[structopt(long, global = true)]
+    pub confirm_remove: Option<usize>,
+    /// Number of rejected words allowed before the game ends [default: unlimited]
+    #[structopt(long, global = true)]
+    pub lives: Option<usize>,
+    /// How words are scored (length, letters, or letters-bonus to multiply by length)
+    #[structopt(long, default_value = "length", global = true)]
+    pub scoring: Scoring,
+    /// Reject words which have already been placed elsewhere on the board
+    #[structopt(long, global = true)]
+    pub no_duplicate_words: bool,
+    /// Let regions wrap around from one edge of the board to the opposite one
+    #[structopt(long, global = true)]
+    pub wrap: bool,
+    /// What shapes regions may take (any, or straight to only allow a single row or column)
+    #[structopt(long, default_value = "any", global = true)]
+    pub shape: Shape,
+    /// How the letters of a selection are read (reading, or selection to follow the order the
+    /// squares were selected in)
+    #[structopt(long, default_value = "reading", global = true)]
+    pub word_order: WordOrder,
+    /// Where the cursor starts (origin or center, moving to the nearest free square if that one's
+    /// taken, or random to pick any free square)
+    #[structopt(long, default_value = "origin", global = true)]
+    pub cursor_start: CursorStart,
+    /// Only play on part of the board, given as WIDTHxHEIGHT from the top left corner or
+    /// WIDTHxHEIGHT+X+Y from somewhere else
+    #[structopt(long, global = true)]
+    pub play_area: Option<PlayArea>,
+    /// Seed for anything random, such as --cursor-start random, to play the same way as a run which
+    /// printed it [default: a different one each run]
+    #[structopt(long, global = true)]
+    pub seed: Option<u64>,
+    /// Number of columns each square of the board takes up
+    #[structopt(long, default_value = "1", global = true)]
+    pub cell_width: u16,
+    /// Start with the first n of the author's regions already placed, leaving the rest to repair
+    #[structopt(long, global = true)]
+    pub prefill: Option<usize>,
+    /// Enable the puzzle author's overlay, which cycles through every word on the board with p
+    #[structopt(long, global = true)]
+    pub author: bool,
+    /// Colors to suit a dark or light terminal background
+    #[structopt(long, default_value = "dark", global = true)]
+    pub theme: ThemePreset,
+    /// What to do when there are more regions than colors (least-used to reuse the color on the
+    /// fewest regions, extend to add more colors, or warn to go round the colors again)
+    #[structopt(long, default_value = "least-used", global = true)]
+    pub palette_overflow: PaletteOverflow,
+    /// Background of committed regions instead of the theme's (a color name, a number from 0 to
+    /// 255, or none to only color the letters)
+    #[structopt(long, global = true)]
+    pub region_background: Option<Background>,
+    /// Draw over the normal screen instead of the alternate screen, so the final board is left in
+    /// the scrollback after quitting
+    #[structopt(long, global = true)]
+    pub inline: bool,
+    /// Ring the terminal bell when the board is completed
+    #[structopt(long, global = true)]
+    pub bell: bool,
+    /// Show a bar under the board filling up as squares are covered
+    #[structopt(long, global = true)]
+    pub progress_bar: bool,
+    /// Show how many letters are selected next to the word lengths allowed (can be toggled with k)
+    #[structopt(long, global = true)]
+    pub show_length: bool,
+    /// After selecting a square, move the cursor on in the direction it last moved (can be
+    /// toggled with t)
+    #[structopt(long, global = true)]
+    pub auto_advance: bool,
+    /// Draw the board turned clockwise by this many quarter turns
+    #[structopt(long, default_value = "0", global = true)]
+    pub rotate: u8,
+    /// Draw the board flipped (horizontal to swap the top and bottom, or vertical to swap the
+    /// left and right), before any --rotate
+    #[structopt(long, global = true)]
+    pub mirror: Option<Axis>,
+}
+
+impl Config {
+    fn cell_width(&self) -> u16 {
+        self.cell_width.max(1)
+    }
+
+    /// Applies the options which change the rules of the game.
+    pub fn configure_ruleset(&self, ruleset: &mut Ruleset) {
+        ruleset.scoring = self.scoring;
+        ruleset.allow_duplicate_words = !self.no_duplicate_words;
+        ruleset.shape = self.shape;
+        if self.wrap {
+            ruleset.topology = Box::new(Toroidal);
+        }
+    }
+
+    /// The board as it's drawn, after --mirror and --rotate.
+    fn view(&self, board: &Board) -> Board {
+        match self.mirror {
+            Some(axis) => board.mirrored(axis).rotated(self.rotate),
+            None => board.rotated(self.rotate),
+        }
+    }
+
+    /// Where `square` of `board` is drawn.
+    fn view_square(&self, square: Square, board: &Board) -> Square {
+        let (width, height) = (board.width(), board.height());
+        let square = match self.mirror {
+            Some(axis) => square.mirrored(axis, width, height),
+            None => square,
+        };
+
+        square.rotated(self.rotate, width, height)
+    }
+
+    /// Which square of `board` is drawn at `square`, undoing [`Config::view_square`].
+    fn game_square(&self, square: Square, board: &Board) -> Square {
+        let (width, height) = (board.width(), board.height());
+        let (view_width, view_height) = match self.rotate % 2 {
+            0 => (width, height),
+            _ => (height, width),
+        };
+        let square = square.rotated(4 - self.rotate % 4, view_width, view_height);
+
+        match self.mirror {
+            Some(axis) => square.mirrored(axis, width, height),
+            None => square,
+        }
+    }
+
+    /// Starts a game on `board`, kept to the --play-area if there is one.
+    pub fn new_game<'a>(
+        &self,
+        board: &'a Board,
+        ruleset: &'a Ruleset,
+    ) -> Result<Game<'a, RegionData>, Error> {
+        Ok(match &self.play_area {
+            Some(play_area) => Game::with_play_area(board, ruleset, play_area.region(board)?),
+            None => Game::new(board, ruleset),
+        })
+    }
+
+    /// Whether anything about the game is left to chance, so the seed matters.
+    pub fn is_random(&self) -> bool {
+        self.cursor_start == CursorStart::Random
+    }
+
+    fn progress_height(&self) -> u16 {
+        match self.progress_bar {
+            true => 1,
+            false => 0,
+        }
+    }
+
+    fn status_height(&self) -> u16 {
+        match (self.status_height, self.verbose_status) {
+            (Some(height), _) => height,
+            (None, false) => 1,
+            (None, true) => 4,
+        }
+    }
+}
+
+const LOG_CAPACITY: usize = 50;
+const LOG_WIDTH: u16 = 32;
+
+const HELP: &[(&str, &str)] = &[
+    ("arrows/wasd", "move the cursor"),
+    ("space", "add/remove square from selection"),
+    ("z", "remove the last square added to the selection"),
+    ("v", "select a straight line from an anchor"),
+    ("enter", "commit the selection"),
+    ("backspace", "delete the region under the cursor"),
+    ("delete", "delete the region under the cursor,"),
+    ("", "or clear the selection if there isn't one"),
+    ("insert", "lift the region under the cursor"),
+    ("ctrl+z/y", "undo or redo adding and removing regions"),
+    ("R", "clear the board, after asking first"),
+    ("tab", "jump between regions"),
+    ("f", "show words with only one placement"),
+    ("m", "show the longest word left to place"),
+    ("l", "show the log"),
+    ("g", "show row and column labels"),
+    ("t", "toggle moving on after selecting a square"),
+    ("k", "show how many letters are selected"),
+    ("h", "highlight squares with the letter under the cursor"),
+    ("r", "show the letters left on free squares"),
+    ("o", "focus on the region under the cursor"),
+    ("c", "change the color of the region under the cursor"),
+    ("e", "edit the region under the cursor: space drops"),
+    ("", "squares, enter applies and esc cancels"),
+    ("/", "find where a typed word fits"),
+    ("i", "select a word which fits, as a hint"),
+    ("n", "skip the puzzle (marathon only)"),
+    ("p", "cycle every word on the board (--author)"),
+    ("?", "show this help"),
+    ("q/esc", "quit"),
+];
+
+/// Names a square like a spreadsheet cell, with lettered columns and numbered rows (e.g. B2).
+fn square_name(square: Square) -> String {
+    format!("{}{}", column_name(square.x), square.y + 1)
+}
+
+fn column_name(x: usize) -> String {
+    let mut column = String::new();
+    let mut x = x + 1;
+    while x > 0 {
+        let letter = b'A' + ((x - 1) % 26) as u8;
+        column.insert(0, letter as char);
+        x = (x - 1) / 26;
+    }
+
+    column
+}
+
+/// The room needed to the left of and above the board for row numbers and column letters.
+fn label_gutters(board: &Board) -> (u16, u16) {
+    let row_width = board.height().to_string().len();
+    let column_height = column_name(board.width() - 1).len();
+
+    (row_width as u16, column_height as u16)
+}
+
+/// The room the board takes up, including its border and any labels.
+fn board_size(board: &Board, cell_width: u16, show_labels: bool) -> (u16, u16) {
+    let (gutter_x, gutter_y) = match show_labels {
+        true => label_gutters(board),
+        false => (0, 0),
+    };
+    let width = gutter_x + (board.width() as u16 + 2) * cell_width;
+    let height = gutter_y + board.height() as u16 + 2;
+
+    (width, height)
+}
+
+/// What's known about the puzzle being played, beyond the board itself.
+pub struct Puzzle {
+    /// The official puzzle number and release date, which puzzles from elsewhere don't have.
+    pub id: Option<i64>,
+    pub date: Option<NaiveDate>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub words: usize,
+    /// The author's regions, in the order the puzzle lists them.
+    pub solution: Vec<Region>,
+    /// The median time the puzzle has taken to solve before, if it has been.
+    pub median_time: Option<Duration>,
+    /// Where the puzzle was left off last time, to carry on from.
+    pub progress: Option<Progress>,
+}
+
+/// A game saved part way through, or after it was finished.
+pub struct Progress {
+    pub regions: Vec<SavedRegion>,
+    pub elapsed: Duration,
+    /// How many saved regions were left out already, for not fitting on the board.
+    pub dropped: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SavedRegion {
+    pub region: Region,
+    /// How far into the game the region was placed.
+    pub placed_at: Duration,
+    /// The color the region was drawn in, or `None` to pick one as usual.
+    pub color: Option<Color>,
+}
+
+impl Progress {
+    /// Whether the regions cover every square of the board `game` is played on.
+    pub fn covers<D>(&self, game: &Game<D>) -> bool {
+        let covered = self.regions.iter().map(|saved| saved.region.size());
+        covered.sum::<usize>() == game.playable_squares()
+    }
+}
+
+impl Puzzle {
+    fn describe(&self, board: &Board) -> String {
+        let Puzzle {
+            id,
+            date,
+            title,
+            author,
+            words,
+            ..
+        } = self;
+
+        let mut description = match id {
+            Some(id) => format!("puzzle {id}"),
+            None => "custom puzzle".to_owned(),
+        };
+        if let Some(date) = date {
+            description += &format!(" ({date})");
+        }
+        if let Some(title) = title {
+            description += &format!(" \"{title}\"");
+        }
+        if let Some(author) = author {
+            description += &format!(" by {author}");
+        }
+        let (width, height) = (board.width(), board.height());
+        description + &format!(" - {width}x{height}, {words} words")
+    }
+}
+
+/// Where a puzzle sits in a marathon of several puzzles played back to back.
+pub struct Marathon {
+    pub puzzle: usize,
+    pub puzzles: usize,
+    pub score: usize,
+}
+
+#[derive(Debug)]
+pub enum Ending {
+    Quit,
+    Skipped,
+    Completed,
+}
+
+/// What the ui keeps about each committed region.
+#[derive(Clone)]
+pub struct RegionData {
+    pub color: Color,
+    /// How far into the game the region was committed.
+    pub placed_at: Duration,
+}
+
+pub struct Outcome<'a> {
+    pub game: Game<'a, RegionData>,
+    pub elapsed: Duration,
+    pub ending: Ending,
+    pub solved: bool,
+}
 
 enum RegionType {
     Committed(Color),
-    Uncommitted,
+    // position of the square in the region's reading order
+    Uncommitted(usize),
+    Hint,
+    // the square the commit animation has reached
+    Trail,
+    // a square being dropped from the region being edited
+    Dropped,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Heading {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// What keys do, which depends on what's going on.
+enum Mode {
+    Normal,
+    Edit(Edit),
+    /// Typing a word to look for, where letters are text rather than commands.
+    Search(String),
+}
+
+impl Mode {
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Edit(_) => "edit",
+            Mode::Search(_) => "search",
+        }
+    }
+}
+
+/// A committed region having squares dropped from it.
+struct Edit {
+    // any square from the region as it was committed, so it can be found even if the square has
+    // been dropped
+    at: Square,
+    kept: Region,
+}
+
+/// How long the commit animation spends on each square.
+const TRAIL_STEP: Duration = Duration::from_millis(40);
+
+/// The animation sweeping along a region's squares in reading order as it's committed.
+struct Trail {
+    region: Region,
+    started: Instant,
+}
+
+impl Trail {
+    /// The square the animation has reached, or `None` once it's finished.
+    fn head(&self) -> Option<Square> {
+        self.head_at(Instant::now())
+    }
+
+    /// The square the animation has reached by `now`.
+    fn head_at(&self, now: Instant) -> Option<Square> {
+        let elapsed = now.saturating_duration_since(self.started);
+        let step = elapsed.as_millis() / TRAIL_STEP.as_millis();
+        self.region.iter_sorted().nth(step as usize)
+    }
+}
+
+struct Theme {
+    committed_background: Option<Color>,
+    // committed regions outside the focused one
+    dimmed: Color,
+    hint: Color,
+    // foreground and background of the commit animation's head
+    trail: (Color, Color),
+    // shades of grey, as indexed colors, for the first and last letters of a selection
+    selection: (u8, u8),
+    // background of the squares sharing the highlighted letter
+    same_letter: Color,
+}
+
+impl Theme {
+    fn new(config: &Config) -> Self {
+        let mut theme = match config.theme {
+            ThemePreset::Dark => Theme {
+                committed_background: Some(Color::DarkGray),
+                dimmed: Color::Gray,
+                hint: Color::LightYellow,
+                trail: (Color::Black, Color::White),
+                selection: (255, 244),
+                same_letter: Color::Indexed(24),
+            },
+            // selections are drawn reversed, so on a light background they need dark shades
+            ThemePreset::Light => Theme {
+                committed_background: Some(Color::Indexed(254)),
+                dimmed: Color::DarkGray,
+                hint: Color::Indexed(130),
+                trail: (Color::White, Color::Black),
+                selection: (232, 243),
+                same_letter: Color::Indexed(153),
+            },
+        };
+
+        if let Some(Background(background)) = config.region_background {
+            theme.committed_background = background;
+        }
+
+        theme
+    }
+}
+
+/// Shades the squares of a region from the theme's first to last shade of grey in reading order,
+/// so it's clear how a selection will be read.
+fn reading_order_color(index: usize, size: usize, (first, last): (u8, u8)) -> Color {
+    let range = last as isize - first as isize;
+
+    let step = match size {
+        0 | 1 => 0,
+        _ => index as isize * range / (size as isize - 1),
+    };
+
+    Color::Indexed((first as isize + step) as u8)
 }
 
 struct GameWidget<'a, 'b, 'c> {
-    game: &'a Game<'b, Color>,
+    game: &'a Game<'b, RegionData>,
     cursor: Square,
     uncommitted: &'c Region,
+    // the squares of the uncommitted region in the order their letters are read
+    uncommitted_order: Vec<Square>,
+    uncommitted_check: &'c Result<(), Vec<CheckRegionError>>,
+    config: &'c Config,
+    elapsed: Duration,
+    median_time: Option<Duration>,
+    message: Option<&'c str>,
+    lives: Option<usize>,
+    log: Option<&'c VecDeque<String>>,
+    marathon: Option<&'c Marathon>,
+    hints: &'c [Region],
+    theme: &'c Theme,
+    done: bool,
+    // some free squares can no longer be filled
+    dead_pocket: bool,
+    // the free squares can't all be filled, even if each could be on its own
+    dead_end: bool,
+    show_help: bool,
+    show_labels: bool,
+    show_remaining: bool,
+    trail_head: Option<Square>,
+    focused: Option<&'c Region>,
+    // the committed region being edited, and the squares of it being kept
+    editing: Option<(&'c Region, &'c Region)>,
+    // the word typed so far while searching
+    search: Option<&'c str>,
+    highlight_letter: Option<char>,
+    show_length: bool,
+}
+
+/// Says how a solve time compares to the usual time, to the nearest percent.
+fn compare_time(elapsed: Duration, median_time: Duration) -> String {
+    let (elapsed, median) = (elapsed.as_secs_f64(), median_time.as_secs_f64());
+    if median == 0.0 {
+        return "same as your average".to_owned();
+    }
+
+    let percent = ((elapsed - median) / median * 100.0).round() as i64;
+    match percent {
+        0 => "same as your average".to_owned(),
+        p if p < 0 => format!("{}% faster than your average", -p),
+        p => format!("{p}% slower than your average"),
+    }
+}
+
+fn word_in_order(board: &Board, order: &[Square]) -> String {
+    order.iter().map(|&square| board.letter(square)).collect()
+}
+
+fn describe_check(
+    game: &Game<RegionData>,
+    region: &Region,
+    word: &str,
+    check: &Result<(), Vec<CheckRegionError>>,
+) -> String {
+    let errors = match check {
+        Ok(_) => {
+            let score = game.score_region(region);
+            return format!("\"{word}\" ({score} points)");
+        }
+        Err(errors) => errors,
+    };
+
+    let describe = |error: &CheckRegionError| match error {
+        CheckRegionError::TooShort => "word too short".to_owned(),
+        CheckRegionError::TooLong => "word too long".to_owned(),
+        CheckRegionError::OutOfBounds => "region out of bounds (wtf)".to_owned(),
+        CheckRegionError::Overlapping => "region overlapping (wtf)".to_owned(),
+        CheckRegionError::NotContiguous => {
+            let pieces = game.ruleset().topology.piece_count(region, game.board());
+            format!("region must be contiguous ({pieces} pieces)")
+        }
+        CheckRegionError::NotStraight => "region must be a straight line".to_owned(),
+        CheckRegionError::NotInDictionary => format!("unknown word \"{word}\""),
+        CheckRegionError::DuplicateWord => format!("\"{word}\" is already on the board"),
+    };
+    errors.iter().map(describe).collect::<Vec<_>>().join("; ")
 }
 
 impl GameWidget<'_, '_, '_> {
@@ -35,48 +845,139 @@ impl GameWidget<'_, '_, '_> {
             game,
             cursor,
             uncommitted,
+            uncommitted_order,
+            hints,
+            config,
+            show_labels,
+            trail_head,
+            focused,
+            theme,
+            editing,
+            highlight_letter,
+            ..
         } = self;
 
-        let game_width = game.board().width();
-        let game_height = game.board().height();
+        // everything is laid out on the board as it's drawn, and mapped back to the game's squares
+        let view = config.view(game.board());
+        let game_width = view.width();
+        let game_height = view.height();
+
+        // every square (and the border around them) is cell_width columns wide, with the letter in
+        // the middle
+        let cell_width = config.cell_width();
+        let padding = (cell_width - 1) / 2;
+
+        let (gutter_x, gutter_y) = match show_labels {
+            true => label_gutters(&view),
+            false => (0, 0),
+        };
+        let (width, height) = board_size(&view, cell_width, *show_labels);
+        assert!(area.width >= width);
+        assert!(area.height >= height);
+
+        // center the board and its labels together, then step past the labels to the board's border
+        let top_left_x = area.x + (area.width - width) / 2 + gutter_x;
+        let top_left_y = area.y + (area.height - height) / 2 + gutter_y;
+        let cell_x = |x: usize| top_left_x + x as u16 * cell_width;
+
+        if *show_labels {
+            let style = Style::default().fg(Color::DarkGray);
 
-        assert!(area.width >= game_width as u16 + 2);
-        assert!(area.height >= game_height as u16 + 2);
+            // column names are written downwards, ending just above the board
+            for x in 0..game_width {
+                let buf_x = cell_x(x + 1) + padding;
+                for (i, c) in column_name(x).chars().rev().enumerate() {
+                    let buf_y = top_left_y - 1 - i as u16;
+                    buf.get_mut(buf_x, buf_y).set_char(c).set_style(style);
+                }
+            }
 
-        let top_left_x = area.x + (area.width - game_width as u16 + 2) / 2;
-        let top_left_y = area.y + (area.height - game_height as u16 + 2) / 2;
+            for y in 0..game_height {
+                let row = (y + 1).to_string();
+                let buf_x = top_left_x - row.len() as u16;
+                let buf_y = top_left_y + y as u16 + 1;
+                buf.set_string(buf_x, buf_y, row, style);
+            }
+        }
 
-        let square_to_region_type = game
+        // the committed region under the cursor stands out a little, to show how far it goes
+        let hovered = game
             .regions()
-            .flat_map(|(region, color)| {
+            .find_map(|(region, _)| region.contains(*cursor).then_some(region));
+
+        // later entries win, so the uncommitted region is drawn over any hints
+        let square_to_region_type = hints
+            .iter()
+            .flat_map(|region| region.squares().map(|square| (square, RegionType::Hint)))
+            .chain(game.regions().flat_map(|(region, data)| {
                 region
                     .squares()
-                    .map(move |square| (square, RegionType::Committed(*color)))
-            })
+                    .map(move |square| (square, RegionType::Committed(data.color)))
+            }))
             .chain(
-                uncommitted
-                    .squares()
-                    .map(|square| (square, RegionType::Uncommitted)),
+                uncommitted_order
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .map(|(i, square)| (square, RegionType::Uncommitted(i))),
             )
+            .chain(trail_head.map(|square| (square, RegionType::Trail)))
+            .chain(editing.iter().flat_map(|(region, kept)| {
+                region
+                    .squares()
+                    .filter(|&square| !kept.contains(square))
+                    .map(|square| (square, RegionType::Dropped))
+            }))
             .collect::<HashMap<_, _>>();
 
         for y in 0..game_height {
             for x in 0..game_width {
-                let square = (x, y).into();
-                let c = game.board().get(square);
+                let square = config.game_square((x, y).into(), game.board());
+                let c = game.board().letter(square);
                 let region_type = square_to_region_type.get(&square);
 
+                let is_focused = focused.map(|region| region.contains(square));
+                let committed_background = theme.committed_background.unwrap_or(Color::Reset);
                 let (fg, bg) = match region_type {
-                    Some(RegionType::Committed(color)) => (*color, Color::DarkGray),
-                    _ => (Color::Reset, Color::Reset),
+                    // while a region is focused, the others lose their colors
+                    Some(RegionType::Committed(_)) if is_focused == Some(false) => {
+                        (theme.dimmed, committed_background)
+                    }
+                    Some(RegionType::Committed(color)) => (*color, committed_background),
+                    Some(RegionType::Uncommitted(i)) => (
+                        reading_order_color(*i, uncommitted.size(), theme.selection),
+                        Color::Reset,
+                    ),
+                    Some(RegionType::Hint) => (theme.hint, Color::Reset),
+                    Some(RegionType::Trail) => theme.trail,
+                    Some(RegionType::Dropped) => (theme.dimmed, Color::Reset),
+                    None if !game.is_playable(square) => (theme.dimmed, Color::Reset),
+                    None => (Color::Reset, Color::Reset),
+                };
+                // selections are drawn reversed, so they'd end up with the highlight as their letters
+                let bg = match region_type {
+                    Some(RegionType::Uncommitted(_)) => bg,
+                    _ if *highlight_letter == Some(c) => theme.same_letter,
+                    _ => bg,
                 };
-                let modifier_cursor = if x == cursor.x && y == cursor.y {
+                let modifier_cursor = if square == *cursor {
                     Modifier::UNDERLINED
                 } else {
                     Modifier::empty()
                 };
                 let modifier_uncommitted = match region_type {
-                    Some(RegionType::Uncommitted) => Modifier::REVERSED,
+                    Some(RegionType::Committed(_)) => match is_focused {
+                        Some(true) => Modifier::BOLD,
+                        Some(false) => Modifier::DIM,
+                        None if hovered.is_some_and(|region| region.contains(square)) => {
+                            Modifier::BOLD
+                        }
+                        None => Modifier::empty(),
+                    },
+                    Some(RegionType::Uncommitted(0)) => Modifier::REVERSED | Modifier::BOLD,
+                    Some(RegionType::Uncommitted(_)) => Modifier::REVERSED,
+                    Some(RegionType::Hint) => Modifier::BOLD,
+                    Some(RegionType::Dropped) => Modifier::CROSSED_OUT,
                     _ => Modifier::empty(),
                 };
                 let style = Style::default()
@@ -84,11 +985,13 @@ impl GameWidget<'_, '_, '_> {
                     .bg(bg)
                     .add_modifier(modifier_cursor | modifier_uncommitted);
 
-                let buf_x = top_left_x + x as u16 + 1;
                 let buf_y = top_left_y + y as u16 + 1;
-                let cell = buf.get_mut(buf_x, buf_y);
-                cell.set_char(c);
-                cell.set_style(style);
+                for i in 0..cell_width {
+                    let c = if i == padding { c } else { ' ' };
+                    let cell = buf.get_mut(cell_x(x + 1) + i, buf_y);
+                    cell.set_char(c);
+                    cell.set_style(style);
+                }
             }
         }
 
@@ -99,39 +1002,142 @@ impl GameWidget<'_, '_, '_> {
                 iproduct!([0, game_width + 1], 0..(game_height + 2)),
             );
             for (x, y) in points {
-                let buf_x = top_left_x + x as u16;
                 let buf_y = top_left_y + y as u16;
-                let cell = buf.get_mut(buf_x, buf_y);
-                cell.set_char(' ');
-                cell.set_style(style);
+                for i in 0..cell_width {
+                    let cell = buf.get_mut(cell_x(x) + i, buf_y);
+                    cell.set_char(' ');
+                    cell.set_style(style);
+                }
             }
         }
     }
 
+    fn render_progress(&self, area: Rect, buf: &mut Buffer) {
+        let GameWidget { game, config, .. } = self;
+
+        // as wide as the board and its border
+        let view = config.view(game.board());
+        let width = ((view.width() as u16 + 2) * config.cell_width()).min(area.width);
+        let squares = game.playable_squares();
+        let filled = (game.used_squares() * width as usize / squares) as u16;
+
+        let color = if game.is_complete() {
+            Color::Green
+        } else {
+            Color::Gray
+        };
+        let x = area.x + (area.width - width) / 2;
+        for i in 0..width {
+            let (c, style) = if i < filled {
+                ('█', Style::default().fg(color))
+            } else {
+                ('░', Style::default().fg(Color::DarkGray))
+            };
+            buf.get_mut(x + i, area.y).set_char(c).set_style(style);
+        }
+    }
+
     fn render_status(&self, area: Rect, buf: &mut Buffer) {
         let GameWidget {
-            game, uncommitted, ..
+            game,
+            uncommitted,
+            uncommitted_order,
+            uncommitted_check,
+            config,
+            elapsed,
+            median_time,
+            message,
+            lives,
+            marathon,
+            done,
+            dead_pocket,
+            dead_end,
+            editing,
+            search,
+            show_remaining,
+            show_length,
+            ..
         } = self;
 
-        let status_text = if uncommitted.size() == 0 {
-            "".to_owned()
-        } else {
-            match game.check_region(uncommitted) {
-                Ok(_) => {
-                    let word = uncommitted.word(game.board());
-                    format!("\"{word}\"")
-                }
-                Err(CheckRegionError::TooShort) => "word too short".to_owned(),
-                Err(CheckRegionError::TooLong) => "word too long".to_owned(),
-                Err(CheckRegionError::OutOfBounds) => "region out of bounds (wtf)".to_owned(),
-                Err(CheckRegionError::Overlapping) => "region overlapping (wtf)".to_owned(),
-                Err(CheckRegionError::NotContiguous) => "region must be contiguous".to_owned(),
-                Err(CheckRegionError::NotInDictionary) => {
-                    let word = uncommitted.word(game.board());
-                    format!("unknown word \"{word}\"")
+        let status_text = match (lives, message, search, editing) {
+            _ if *done => {
+                let score = game.score();
+                let seconds = elapsed.as_secs();
+                let (minutes, seconds) = (seconds / 60, seconds % 60);
+
+                let comparison = match median_time {
+                    Some(median_time) => format!(" ({})", compare_time(*elapsed, *median_time)),
+                    None => String::new(),
+                };
+
+                format!(
+                    "complete! score {score} in {minutes:02}:{seconds:02}{comparison} - press any key to quit"
+                )
+            }
+            (Some(0), _, _, _) => "out of lives - press q to quit".to_owned(),
+            (_, Some(message), _, _) => message.to_string(),
+            (_, _, Some(word), _) => format!("find: {word}_ - enter searches, esc cancels"),
+            (_, _, _, Some((region, kept))) => {
+                let (word, kept) = (region.word(game.board()), kept.word(game.board()));
+                format!("editing {word} into {kept} - space drops, enter applies, esc cancels")
+            }
+            // with nothing selected, there's room to show what's left to be placed
+            _ if uncommitted.size() == 0 && *show_remaining => {
+                let letters = game.free_letters().into_iter().map(String::from);
+                format!("remaining: {}", letters.collect::<Vec<_>>().join(" "))
+            }
+            _ if uncommitted.size() == 0 => "".to_owned(),
+            _ => {
+                let word = word_in_order(game.board(), uncommitted_order);
+                let check = describe_check(game, uncommitted, &word, uncommitted_check);
+                if *show_length {
+                    let Ruleset {
+                        min_length,
+                        max_length,
+                        ..
+                    } = game.ruleset();
+                    let length = uncommitted.size();
+                    let need = if min_length == max_length {
+                        min_length.to_string()
+                    } else {
+                        format!("{min_length}-{max_length}")
+                    };
+                    format!("[{length} letters, need {need}] {check}")
+                } else {
+                    check
                 }
             }
         };
+        let status_text = if *dead_pocket && !*done {
+            format!("[dead pocket] {status_text}")
+        } else if *dead_end && !*done {
+            format!("[no valid completion remains] {status_text}")
+        } else {
+            status_text
+        };
+        let status_text = match lives {
+            Some(lives) if *lives > 0 => format!("[lives: {lives}] {status_text}"),
+            _ => status_text,
+        };
+        let status_text = match marathon {
+            Some(Marathon {
+                puzzle,
+                puzzles,
+                score,
+            }) => format!("[puzzle {puzzle} of {puzzles}, score {score}] {status_text}"),
+            None => status_text,
+        };
+
+        let status_text = if config.verbose_status {
+            let regions = game.regions().count();
+            let score = game.score();
+            let seconds = elapsed.as_secs();
+            let (minutes, seconds) = (seconds / 60, seconds % 60);
+
+            format!("{status_text}\nregions: {regions}\nscore: {score}\ntime: {minutes:02}:{seconds:02}")
+        } else {
+            status_text
+        };
 
         Paragraph::new(status_text)
             .alignment(Alignment::Center)
@@ -140,30 +1146,145 @@ impl GameWidget<'_, '_, '_> {
     }
 }
 
+impl GameWidget<'_, '_, '_> {
+    fn render_log(&self, area: Rect, buf: &mut Buffer) {
+        let log = match self.log {
+            Some(log) => log,
+            None => return,
+        };
+
+        // show as many of the most recent messages as fit, oldest at the top
+        let block = Block::default().title("log").borders(Borders::ALL);
+        let lines = block.inner(area).height as usize;
+        let skip = log.len().saturating_sub(lines);
+        let text = log
+            .iter()
+            .skip(skip)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Paragraph::new(text).block(block).render(area, buf);
+    }
+}
+
 impl<'a, 'b, 'c> Widget for GameWidget<'a, 'b, 'c> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let status_height = Constraint::Length(self.config.status_height());
+        let progress_height = Constraint::Length(self.config.progress_height());
+        let board = Constraint::Min(0);
+        let (constraints, board_chunk, progress_chunk, status_chunk) =
+            match self.config.status_position {
+                StatusBarPosition::Top => ([status_height, board, progress_height], 1, 2, 0),
+                StatusBarPosition::Bottom => ([board, progress_height, status_height], 0, 1, 2),
+            };
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .constraints(constraints)
             .split(area);
 
-        self.render_board(chunks[0], buf);
-        self.render_status(chunks[1], buf);
+        let board_area = if self.log.is_some() {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(LOG_WIDTH)])
+                .split(chunks[board_chunk]);
+
+            self.render_log(chunks[1], buf);
+            chunks[0]
+        } else {
+            chunks[board_chunk]
+        };
+
+        self.render_board(board_area, buf);
+        if self.config.progress_bar {
+            self.render_progress(chunks[progress_chunk], buf);
+        }
+        self.render_status(chunks[status_chunk], buf);
+
+        if self.show_help {
+            render_help(area, buf);
+        }
     }
 }
 
-struct App<'a> {
-    game: Game<'a, Color>,
-    colors: Vec<Color>,
-    all_colors: Vec<Color>,
-    cursor: Square,
-    uncommitted: Region,
-    running: bool,
+fn render_help(area: Rect, buf: &mut Buffer) {
+    let key_width = HELP.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    let text = HELP
+        .iter()
+        .map(|(key, description)| format!("{key:>key_width$}  {description}"))
+        .collect::<Vec<_>>();
+
+    let width = text
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let height = text.len() as u16 + 2;
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    let help_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    Clear.render(help_area, buf);
+    Paragraph::new(text.join("\n"))
+        .block(Block::default().title("help").borders(Borders::ALL))
+        .render(help_area, buf);
 }
 
-impl<'a> App<'a> {
-    fn new(game: Game<'a, Color>) -> Self {
-        let all_colors = vec![
+/// What to do once every color in the palette is on the board.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PaletteOverflow {
+    /// Reuse the color on the fewest regions.
+    LeastUsed,
+    /// Add colors from the 256 color palette, each different from the ones already used.
+    Extend,
+    /// Go round the palette in order again, with a warning that colors are being shared.
+    Warn,
+}
+
+impl FromStr for PaletteOverflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "least-used" => Ok(Self::LeastUsed),
+            "extend" => Ok(Self::Extend),
+            "warn" => Ok(Self::Warn),
+            _ => Err(format!("unknown palette overflow \"{s}\"")),
+        }
+    }
+}
+
+/// The `n`th of the extra colors for [`PaletteOverflow::Extend`], skipping the darkest shades and
+/// the grays of the 6x6x6 color cube, and stepping through it so neighbours look different.
+fn extra_color(n: usize) -> Option<Color> {
+    // each channel runs 1 to 5, and 37 shares no factors with 125 so every color comes up once
+    (n..125)
+        .map(|n| (n * 37) % 125)
+        .map(|i| (i / 25 + 1, i / 5 % 5 + 1, i % 5 + 1))
+        .find(|&(r, g, b)| !(r == g && g == b))
+        .map(|(r, g, b)| Color::Indexed((16 + 36 * r + 6 * g + b) as u8))
+}
+
+struct Palette {
+    colors: Vec<Color>,
+    counts: Vec<usize>,
+    last_used: Vec<usize>,
+    commit_count: usize,
+    overflow: PaletteOverflow,
+    // how far through the extra colors we've got
+    extended: usize,
+}
+
+impl Palette {
+    fn new(overflow: PaletteOverflow) -> Self {
+        let colors = vec![
             Color::Red,
             Color::Green,
             Color::Yellow,
@@ -173,135 +1294,2604 @@ impl<'a> App<'a> {
         ];
 
         Self {
+            counts: vec![0; colors.len()],
+            last_used: vec![0; colors.len()],
+            colors,
+            commit_count: 0,
+            overflow,
+            extended: 0,
+        }
+    }
+
+    /// Whether every color is on at least one region, so the next one will have to be shared (or,
+    /// with [`PaletteOverflow::Extend`], made up).
+    fn is_exhausted(&self) -> bool {
+        self.counts.iter().all(|&count| count > 0)
+    }
+
+    fn next_color(&mut self) -> Color {
+        if self.is_exhausted() && self.overflow == PaletteOverflow::Extend {
+            while let Some(color) = extra_color(self.extended) {
+                self.extended += 1;
+                if !self.colors.contains(&color) {
+                    self.colors.push(color);
+                    self.counts.push(0);
+                    self.last_used.push(0);
+                    return self.use_color(color);
+                }
+            }
+        }
+
+        // prefer the color on the fewest committed regions, then the one we used longest ago, and
+        // finally palette order so the choice is always deterministic. Going round in order just
+        // skips the first of those.
+        let index = match self.overflow {
+            PaletteOverflow::Warn if self.is_exhausted() => (0..self.colors.len())
+                .min_by_key(|&i| (self.last_used[i], i))
+                .unwrap(),
+            _ => (0..self.colors.len())
+                .min_by_key(|&i| (self.counts[i], self.last_used[i], i))
+                .unwrap(),
+        };
+
+        self.use_color(self.colors[index])
+    }
+
+    /// Counts `color` as used by a newly committed region, which may not be one of the palette's
+    /// own colors if it came from elsewhere.
+    fn use_color(&mut self, color: Color) -> Color {
+        self.commit_count += 1;
+        if let Some(index) = self.colors.iter().position(|&c| c == color) {
+            self.counts[index] += 1;
+            self.last_used[index] = self.commit_count;
+        }
+
+        color
+    }
+
+    /// Swaps `color` for the one after it in the palette.
+    fn cycle_color(&mut self, color: Color) -> Color {
+        let index = match self.colors.iter().position(|&c| c == color) {
+            Some(index) => index,
+            None => return color,
+        };
+        let next = (index + 1) % self.colors.len();
+
+        self.counts[index] -= 1;
+        self.counts[next] += 1;
+
+        self.colors[next]
+    }
+
+    fn release_color(&mut self, color: Color) {
+        if let Some(index) = self.colors.iter().position(|&c| c == color) {
+            self.counts[index] -= 1;
+        }
+    }
+}
+
+struct App<'a> {
+    game: Game<'a, RegionData>,
+    palette: Palette,
+    cursor: Square,
+    last_committed: Option<Square>,
+    anchor: Option<Square>,
+    uncommitted: Region,
+    // the same squares as the uncommitted region, in the order they were selected
+    selection: Vec<Square>,
+    // checking a region isn't free, so only do it when the uncommitted region changes rather than
+    // on every frame
+    uncommitted_check: Result<(), Vec<CheckRegionError>>,
+    message: Option<String>,
+    lives: Option<usize>,
+    log: VecDeque<String>,
+    show_log: bool,
+    // finding forced placements means searching the whole board, so hang on to them until the
+    // board changes
+    forced: Option<Vec<Region>>,
+    show_forced: bool,
+    // the same goes for the longest word, which is found on demand and might not exist
+    longest: Option<Option<Region>>,
+    show_longest: bool,
+    // every placement on the whole board, for --author; the board never changes so these are only
+    // found once
+    author_placements: Option<Vec<Region>>,
+    author_index: Option<usize>,
+    // the board has been completed and is being shown off until the next key press
+    done: bool,
+    // whether the free squares have been cut up so the board can't be completed, checked whenever
+    // the board changes
+    dead_pocket: bool,
+    // whether there's no way left to cover the free squares with words, as far as
+    // --completion-budget lets us look
+    dead_end: bool,
+    show_help: bool,
+    show_labels: bool,
+    show_remaining: bool,
+    trail: Option<Trail>,
+    // a square in the committed region being focused on
+    focused: Option<Square>,
+    // a region which removing has been asked for once, and will be removed if it's asked for again
+    // straight away
+    pending_removal: Option<Region>,
+    // whether clearing the board has been asked for once, the same way
+    pending_clear: bool,
+    show_length: bool,
+    // every square with this letter is highlighted
+    highlight_letter: Option<char>,
+    // which way the cursor last moved, for --auto-advance
+    heading: Option<Heading>,
+    auto_advance: bool,
+    mode: Mode,
+    // where the last search found its word, shown until the next key press
+    found: Vec<Region>,
+    running: bool,
+    ending: Ending,
+    marathon: Option<Marathon>,
+    puzzle: Puzzle,
+    theme: Theme,
+    config: Config,
+    started: Instant,
+    finished: Option<Duration>,
+    // the board has just been completed, and with --bell the terminal should be told about it
+    bell: bool,
+    rng: Rng,
+}
+
+impl<'a> App<'a> {
+    fn new(
+        game: Game<'a, RegionData>,
+        puzzle: Puzzle,
+        config: Config,
+        marathon: Option<Marathon>,
+    ) -> Self {
+        let uncommitted = Region::new();
+        let uncommitted_check = game.check_region_all(&uncommitted).map(|_| ());
+        // show what's being played until the first key press
+        let message = Some(puzzle.describe(game.board()));
+        let seed = config.seed.unwrap_or_else(Rng::random_seed);
+        log::info!("random seed {seed}");
+
+        let mut app = Self {
             game,
-            colors: all_colors.clone(),
-            all_colors,
+            palette: Palette::new(config.palette_overflow),
             cursor: (0, 0).into(),
-            uncommitted: Region::new(),
+            last_committed: None,
+            anchor: None,
+            uncommitted,
+            selection: vec![],
+            uncommitted_check,
+            message,
+            lives: config.lives,
+            log: VecDeque::with_capacity(LOG_CAPACITY),
+            show_log: false,
+            forced: None,
+            show_forced: false,
+            longest: None,
+            show_longest: false,
+            author_placements: None,
+            author_index: None,
+            done: false,
+            dead_pocket: false,
+            dead_end: false,
+            show_help: false,
+            show_labels: false,
+            show_remaining: false,
+            trail: None,
+            focused: None,
+            pending_removal: None,
+            pending_clear: false,
+            show_length: config.show_length,
+            highlight_letter: None,
+            heading: None,
+            auto_advance: config.auto_advance,
+            mode: Mode::Normal,
+            found: vec![],
             running: true,
+            ending: Ending::Quit,
+            marathon,
+            puzzle,
+            theme: Theme::new(&config),
+            config,
+            started: Instant::now(),
+            finished: None,
+            bell: false,
+            rng: Rng::new(seed),
+        };
+        match app.puzzle.progress.take() {
+            Some(progress) => app.resume(progress),
+            None => app.prefill(),
+        }
+        // what the game starts with isn't for undoing
+        app.game.clear_history();
+        // a board which starts out complete wasn't just finished
+        app.bell = false;
+        app.cursor = app.config.cursor_start.square(&app.game, &mut app.rng);
+
+        app
+    }
+
+    /// Puts back the regions of a saved game and winds the clock on to where it was, so a finished
+    /// puzzle shows up finished, time and all.
+    fn resume(&mut self, progress: Progress) {
+        let mut dropped = progress.dropped;
+        for SavedRegion {
+            region,
+            placed_at,
+            color,
+        } in &progress.regions
+        {
+            // the region may have been read as any of the words it spells when it was committed
+            let checked_region = self
+                .game
+                .ruleset()
+                .words_for_region(region, self.game.board())
+                .iter()
+                .find_map(|word| self.game.check_region_as(region, word).ok())
+                // the order the squares were selected in isn't saved, so there's no telling which
+                // word was read, just as there isn't when checking for a win
+                .or_else(|| match self.config.word_order {
+                    WordOrder::ReadingOrder => None,
+                    WordOrder::SelectionOrder => self.game.check_region_unread(region).ok(),
+                });
+            if let Some(checked_region) = checked_region {
+                // keep the colors the regions were saved with, so a shared solve looks the same
+                let color = match color {
+                    Some(color) => self.palette.use_color(*color),
+                    None => self.palette.next_color(),
+                };
+                let placed_at = *placed_at;
+                self.game
+                    .add_region(checked_region, RegionData { color, placed_at });
+            } else {
+                // the rules or the dictionary may have changed since it was saved
+                log::warn!(
+                    "leaving out saved region {}",
+                    region.word(self.game.board())
+                );
+                dropped += 1;
+            }
+        }
+        if dropped > 0 {
+            let regions = if dropped == 1 { "region" } else { "regions" };
+            self.message = Some(format!(
+                "left out {dropped} saved {regions} which no longer fit"
+            ));
         }
+
+        let now = Instant::now();
+        self.started = now.checked_sub(progress.elapsed).unwrap_or(now);
+        self.game_changed();
+        self.done = self.game.regions().next().is_some() && self.is_won();
+    }
+
+    /// Commits the first of the author's regions for --prefill, as if they'd been placed right at
+    /// the start.
+    fn prefill(&mut self) {
+        let count = self.config.prefill.unwrap_or(0);
+        for region in self.puzzle.solution.iter().take(count) {
+            // the dictionary might not have every one of the author's words
+            if let Ok(checked_region) = self.game.check_region(region) {
+                let color = self.palette.next_color();
+                let placed_at = Duration::ZERO;
+                self.game
+                    .add_region(checked_region, RegionData { color, placed_at });
+            }
+        }
+
+        self.game_changed();
+        self.done = count > 0 && self.is_won();
     }
 
     fn on_event(&mut self, event: Event) {
-        match event {
-            Event::Key(key) => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => self.running = false,
-                KeyCode::Char('w') | KeyCode::Up => self.cursor_up(),
-                KeyCode::Char('s') | KeyCode::Down => self.cursor_down(),
-                KeyCode::Char('a') | KeyCode::Left => self.cursor_left(),
-                KeyCode::Char('d') | KeyCode::Right => self.cursor_right(),
-                KeyCode::Char(' ') => self.select(),
-                KeyCode::Enter => self.add(),
-                KeyCode::Delete => self.remove(),
-                KeyCode::Insert => self.remove_and_add(),
-                _ => {}
-            },
+        log::trace!("{event:?}");
+        let mode = self.mode.name();
+
+        self.handle_event(event);
+
+        if self.mode.name() != mode {
+            log::debug!("mode {mode} -> {}", self.mode.name());
+        }
+        if let Some(message) = &self.message {
+            log::debug!("message: {message}");
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        if let Event::Key(key) = event {
+            // messages and focus only last until the next key press, and animations can be
+            // skipped with one
+            self.message = None;
+            self.trail = None;
+            self.found.clear();
+            let focused = self.focused.take();
+            let pending_removal = self.pending_removal.take();
+            let pending_clear = std::mem::take(&mut self.pending_clear);
+
+            // once the lives run out, the only thing left to do is quit
+            if self.lives == Some(0) {
+                if let KeyCode::Char('q') | KeyCode::Esc = key.code {
+                    self.running = false;
+                }
+                return;
+            }
+
+            // any key closes the help, without doing anything else
+            if self.show_help {
+                self.show_help = false;
+                return;
+            }
+
+            let cursor = self.cursor;
+            if self.done {
+                // looking around the finished board is fine, anything else means we're finished
+                match key.code {
+                    KeyCode::Char('w') | KeyCode::Up => self.cursor_up(),
+                    KeyCode::Char('s') | KeyCode::Down => self.cursor_down(),
+                    KeyCode::Char('a') | KeyCode::Left => self.cursor_left(),
+                    KeyCode::Char('d') | KeyCode::Right => self.cursor_right(),
+                    _ => self.end(Ending::Completed),
+                }
+                return;
+            }
+
+            // letters are commands normally, but text when typing a search
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            match self.mode {
+                Mode::Normal if control && key.code == KeyCode::Char('z') => self.undo(),
+                Mode::Normal if control && key.code == KeyCode::Char('y') => self.redo(),
+                Mode::Normal => {
+                    self.on_normal_key(key.code, focused, pending_removal, pending_clear)
+                }
+                Mode::Edit(_) => self.on_edit_key(key.code),
+                Mode::Search(_) => self.on_search_key(key.code),
+            }
+
+            if self.cursor != cursor {
+                self.announce(format!("cursor moved to {}", self.name_of(self.cursor)));
+            }
+        }
+    }
+
+    fn on_normal_key(
+        &mut self,
+        code: KeyCode,
+        focused: Option<Square>,
+        pending_removal: Option<Region>,
+        pending_clear: bool,
+    ) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.running = false,
+            KeyCode::Char('w') | KeyCode::Up => self.cursor_up(),
+            KeyCode::Char('s') | KeyCode::Down => self.cursor_down(),
+            KeyCode::Char('a') | KeyCode::Left => self.cursor_left(),
+            KeyCode::Char('d') | KeyCode::Right => self.cursor_right(),
+            KeyCode::Char(' ') => self.select(),
+            KeyCode::Char('v') => self.select_line(),
+            KeyCode::Enter => self.add(),
+            KeyCode::Backspace => {
+                self.delete_region(pending_removal);
+            }
+            KeyCode::Delete => self.remove(pending_removal),
+            KeyCode::Insert => self.remove_and_add(),
+            KeyCode::Char('R') => self.clear_board(pending_clear),
+            KeyCode::Tab => self.cycle_regions(),
+            KeyCode::Char('l') => self.show_log = !self.show_log,
+            KeyCode::Char('?') => self.show_help = true,
+            KeyCode::Char('g') => self.show_labels = !self.show_labels,
+            KeyCode::Char('t') => self.toggle_auto_advance(),
+            KeyCode::Char('k') => self.show_length = !self.show_length,
+            KeyCode::Char('h') => self.toggle_highlight_letter(),
+            KeyCode::Char('z') => self.deselect_last(),
+            KeyCode::Char('r') => self.show_remaining = !self.show_remaining,
+            // pressing it again just leaves focus cleared
+            KeyCode::Char('o') if focused.is_none() => self.focus(),
+            KeyCode::Char('f') => self.toggle_forced(),
+            KeyCode::Char('m') => self.toggle_longest(),
+            KeyCode::Char('c') => self.recolor(),
+            KeyCode::Char('e') => self.start_edit(),
+            KeyCode::Char('/') => self.mode = Mode::Search(String::new()),
+            KeyCode::Char('i') => self.hint(),
+            KeyCode::Char('p') if self.config.author => self.cycle_author_placements(),
+            KeyCode::Char('n') if self.marathon.is_some() => self.end(Ending::Skipped),
+            _ => {}
+        }
+    }
+
+    fn on_edit_key(&mut self, code: KeyCode) {
+        // while editing a region, only moving and dropping squares work
+        match code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.message = Some("edit cancelled".to_owned());
+            }
+            KeyCode::Char('w') | KeyCode::Up => self.cursor_up(),
+            KeyCode::Char('s') | KeyCode::Down => self.cursor_down(),
+            KeyCode::Char('a') | KeyCode::Left => self.cursor_left(),
+            KeyCode::Char('d') | KeyCode::Right => self.cursor_right(),
+            KeyCode::Char(' ') => self.toggle_edit_square(),
+            KeyCode::Enter => self.apply_edit(),
+            _ => {}
+        }
+    }
+
+    fn on_search_key(&mut self, code: KeyCode) {
+        let word = match &mut self.mode {
+            Mode::Search(word) => word,
+            _ => return,
+        };
+
+        match code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => self.search(),
+            KeyCode::Backspace => {
+                word.pop();
+            }
+            KeyCode::Char(c) => word.extend(c.to_uppercase()),
             _ => {}
         }
     }
 
+    /// The smallest terminal the board and status bar (and the log, if it's open) fit in.
+    fn min_size(&self) -> (u16, u16) {
+        let (width, height) = board_size(
+            &self.config.view(self.game.board()),
+            self.config.cell_width(),
+            self.show_labels,
+        );
+        let log_width = if self.show_log { LOG_WIDTH } else { 0 };
+
+        (
+            width + log_width,
+            height + self.config.progress_height() + self.config.status_height(),
+        )
+    }
+
     fn draw<B: Backend>(&self, f: &mut Frame<'_, B>) {
         let size = f.size();
+
+        // the terminal may have been shrunk mid-game, so ask for more room rather than panicking
+        let (min_width, min_height) = self.min_size();
+        if size.width < min_width || size.height < min_height {
+            let message = format!("terminal too small, need {min_width}x{min_height}");
+            f.render_widget(Paragraph::new(message), size);
+            return;
+        }
+
         let game_widget = GameWidget {
             game: &self.game,
             cursor: self.cursor,
             uncommitted: &self.uncommitted,
+            uncommitted_order: self.uncommitted_order(),
+            uncommitted_check: &self.uncommitted_check,
+            config: &self.config,
+            elapsed: self.elapsed(),
+            median_time: self.puzzle.median_time,
+            message: self.message.as_deref(),
+            lives: self.lives,
+            log: self.show_log.then_some(&self.log),
+            marathon: self.marathon.as_ref(),
+            search: match &self.mode {
+                Mode::Search(word) => Some(word),
+                _ => None,
+            },
+            hints: match (
+                self.author_index,
+                &self.author_placements,
+                self.show_forced,
+                &self.forced,
+            ) {
+                _ if !self.found.is_empty() => &self.found,
+                (Some(i), Some(placements), _, _) => std::slice::from_ref(&placements[i]),
+                _ if self.show_longest => match &self.longest {
+                    Some(Some(longest)) => std::slice::from_ref(longest),
+                    _ => &[],
+                },
+                (_, _, true, Some(forced)) => forced,
+                _ => &[],
+            },
+            done: self.done,
+            dead_pocket: self.dead_pocket,
+            dead_end: self.dead_end,
+            show_help: self.show_help,
+            show_labels: self.show_labels,
+            show_remaining: self.show_remaining,
+            show_length: self.show_length,
+            highlight_letter: self.highlight_letter,
+            theme: &self.theme,
+            trail_head: self.trail.as_ref().and_then(Trail::head),
+            focused: self.focused.and_then(|square| {
+                let mut regions = self.game.regions();
+                regions.find_map(|(region, _)| region.contains(square).then_some(region))
+            }),
+            editing: match &self.mode {
+                Mode::Edit(edit) => Some(edit),
+                _ => None,
+            }
+            .and_then(|edit| {
+                let mut regions = self.game.regions();
+                let region =
+                    regions.find_map(|(region, _)| region.contains(edit.at).then_some(region));
+                region.map(|region| (region, &edit.kept))
+            }),
         };
         f.render_widget(game_widget, size);
     }
 
+    /// Moves time along between events, finishing any animation that's run its course.
+    fn tick(&mut self) {
+        if self.trail.as_ref().and_then(Trail::head).is_none() {
+            self.trail = None;
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.trail.is_some()
+    }
+
+    fn title(&self) -> String {
+        let percent = 100 * self.game.used_squares() / self.game.playable_squares();
+
+        match self.puzzle.id {
+            Some(id) => format!("paperbark — puzzle {id} ({percent}%)"),
+            None => format!("paperbark ({percent}%)"),
+        }
+    }
+
+    /// Whether the puzzle has been solved. Words read in selection order can be any shuffle of a
+    /// region's letters, which can't be checked again afterwards, so then covering the board has
+    /// to do.
+    fn is_won(&self) -> bool {
+        match self.config.word_order {
+            WordOrder::ReadingOrder => self.game.is_solved(),
+            WordOrder::SelectionOrder => self.game.is_complete(),
+        }
+    }
+
     fn is_running(&self) -> bool {
         self.running
     }
 
-    fn cursor_up(&mut self) {
-        if self.cursor.y > 0 {
-            self.cursor.y -= 1;
+    fn end(&mut self, ending: Ending) {
+        log::debug!("ending: {ending:?}");
+        self.ending = ending;
+        self.running = false;
+    }
+
+    fn recheck_uncommitted(&mut self) {
+        self.uncommitted_check = self
+            .game
+            .check_region_all_as(&self.uncommitted, &self.uncommitted_word())
+            .map(|_| ());
+    }
+
+    fn uncommitted_order(&self) -> Vec<Square> {
+        match self.config.word_order {
+            WordOrder::ReadingOrder => self.uncommitted.iter_sorted().collect(),
+            WordOrder::SelectionOrder => self.selection.clone(),
         }
     }
 
-    fn cursor_down(&mut self) {
-        let max_y = self.game.board().height() - 1;
-        if self.cursor.y < max_y {
-            self.cursor.y += 1;
+    fn uncommitted_word(&self) -> String {
+        // a selection can jump about, so it isn't always a path through the region
+        word_in_order(self.game.board(), &self.uncommitted_order())
+    }
+
+    fn add_to_selection(&mut self, square: Square) {
+        if self.uncommitted.add_square(square) {
+            self.selection.push(square);
         }
     }
 
-    fn cursor_left(&mut self) {
-        if self.cursor.x > 0 {
-            self.cursor.x -= 1;
+    fn remove_from_selection(&mut self, square: Square) -> bool {
+        let removed = self.uncommitted.remove_square(square);
+        if removed {
+            self.selection.retain(|&s| s != square);
         }
+
+        removed
+    }
+
+    fn clear_selection(&mut self) {
+        self.uncommitted = Region::new();
+        self.selection.clear();
+        self.recheck_uncommitted();
+    }
+
+    fn announce(&mut self, message: impl Into<String>) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(message.into());
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(|| self.started.elapsed())
+    }
+
+    fn game_changed(&mut self) {
+        log::debug!(
+            "{} regions placed, {} squares covered",
+            self.game.regions().count(),
+            self.game.used_squares()
+        );
+        self.update_timer();
+        self.dead_pocket = self.game.has_unwinnable_pockets();
+        // a dead pocket already says as much, more cheaply
+        self.dead_end = !self.dead_pocket
+            && !self
+                .game
+                .has_possible_completion(self.config.completion_budget);
+
+        self.forced = None;
+        if self.show_forced {
+            self.forced = Some(self.game.forced_placements());
+        }
+
+        self.longest = None;
+        if self.show_longest {
+            self.longest = Some(self.game.longest_placeable());
+        }
+    }
+
+    fn start_edit(&mut self) {
+        let region = self
+            .game
+            .regions()
+            .find_map(|(region, _)| region.contains(self.cursor).then_some(region));
+
+        if let Some(region) = region {
+            self.mode = Mode::Edit(Edit {
+                at: self.cursor,
+                kept: region.clone(),
+            });
+        }
+    }
+
+    fn toggle_edit_square(&mut self) {
+        let edit = match &mut self.mode {
+            Mode::Edit(edit) => edit,
+            _ => return,
+        };
+        let is_in_region = self
+            .game
+            .regions()
+            .any(|(region, _)| region.contains(edit.at) && region.contains(self.cursor));
+
+        // only the region's own squares can be dropped, or put back
+        if is_in_region && !edit.kept.remove_square(self.cursor) {
+            edit.kept.add_square(self.cursor);
+        }
+    }
+
+    fn apply_edit(&mut self) {
+        let edit = match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Edit(edit) => edit,
+            mode => {
+                self.mode = mode;
+                return;
+            }
+        };
+
+        match self.game.replace_region(edit.at, &edit.kept) {
+            Some(Ok(())) => {
+                let word = edit.kept.word(self.game.board());
+                self.announce(format!("edited region into {word}"));
+                self.game_changed();
+            }
+            Some(Err(e)) => {
+                let word = edit.kept.word(self.game.board());
+                let reason = describe_check(&self.game, &edit.kept, &word, &Err(vec![e]));
+                self.message = Some(format!("can't edit: {reason}"));
+                // stay in the edit so the squares can be fixed up
+                self.mode = Mode::Edit(edit);
+            }
+            None => {}
+        }
+    }
+
+    fn search(&mut self) {
+        let word = match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Search(word) => word,
+            mode => {
+                self.mode = mode;
+                return;
+            }
+        };
+        if word.is_empty() {
+            return;
+        }
+
+        let board = self.game.board();
+        self.found = self
+            .game
+            .placements()
+            .into_iter()
+            .filter(|region| region.word(board) == word)
+            .collect();
+
+        self.message = Some(match self.found.len() {
+            0 => format!("{word} doesn't fit anywhere"),
+            1 => format!("{word} fits in 1 place"),
+            n => format!("{word} fits in {n} places"),
+        });
+    }
+
+    /// Swaps the selection for a region which could be committed, leaving the player to commit it.
+    fn hint(&mut self) {
+        let region = match self.game.hint() {
+            Some(region) => region,
+            None => {
+                self.message = Some("no words fit anywhere".to_owned());
+                return;
+            }
+        };
+
+        self.clear_selection();
+        for square in region.iter_sorted() {
+            self.add_to_selection(square);
+        }
+        self.recheck_uncommitted();
+        if let Some(centroid) = region.centroid_square() {
+            self.cursor = centroid;
+        }
+
+        let word = region.word(self.game.board());
+        self.announce(format!("hinted {word}"));
+        self.message = Some(format!("{word} fits here, press enter to commit it"));
+    }
+
+    fn recolor(&mut self) {
+        if let Some(data) = self.game.region_data_mut(self.cursor) {
+            data.color = self.palette.cycle_color(data.color);
+        }
+    }
+
+    fn focus(&mut self) {
+        if !self.game.is_square_free(self.cursor) {
+            self.focused = Some(self.cursor);
+        }
+    }
+
+    fn toggle_forced(&mut self) {
+        self.show_forced = !self.show_forced;
+        if !self.show_forced {
+            return;
+        }
+
+        let forced = self
+            .forced
+            .get_or_insert_with(|| self.game.forced_placements());
+        self.message = Some(format!("{} forced placements", forced.len()));
+    }
+
+    fn toggle_longest(&mut self) {
+        self.show_longest = !self.show_longest;
+        if !self.show_longest {
+            return;
+        }
+
+        let board = self.game.board();
+        let longest = self
+            .longest
+            .get_or_insert_with(|| self.game.longest_placeable());
+        self.message = Some(match longest {
+            Some(region) => format!(
+                "longest word left: {} ({} letters)",
+                region.word(board),
+                region.size()
+            ),
+            None => "no words left to place".to_owned(),
+        });
+    }
+
+    /// Steps the author's overlay on to the next placement, turning it off after the last one.
+    fn cycle_author_placements(&mut self) {
+        let placements = self
+            .author_placements
+            .get_or_insert_with(|| self.game.all_placements());
+
+        self.author_index = match self.author_index {
+            None if !placements.is_empty() => Some(0),
+            Some(i) if i + 1 < placements.len() => Some(i + 1),
+            _ => None,
+        };
+
+        self.message = Some(match self.author_index {
+            Some(i) => {
+                let word = placements[i].word(self.game.board());
+                format!("placement {} of {}: {word}", i + 1, placements.len())
+            }
+            None => format!("{} placements on the board", placements.len()),
+        });
+    }
+
+    fn update_timer(&mut self) {
+        // stop the clock once the board is complete, and restart it if a region is removed
+        let was_complete = self.finished.is_some();
+        self.finished = if self.game.is_complete() {
+            Some(self.elapsed())
+        } else {
+            None
+        };
+
+        if !was_complete && self.finished.is_some() && self.config.bell {
+            self.bell = true;
+        }
+    }
+
+    /// Whether to ring the bell, which is only done once each time the board is completed.
+    fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    fn cursor_up(&mut self) {
+        self.move_cursor(Heading::Up);
+    }
+
+    fn cursor_down(&mut self) {
+        self.move_cursor(Heading::Down);
+    }
+
+    fn cursor_left(&mut self) {
+        self.move_cursor(Heading::Left);
     }
 
     fn cursor_right(&mut self) {
-        let max_x = self.game.board().width() - 1;
-        if self.cursor.x < max_x {
-            self.cursor.x += 1;
+        self.move_cursor(Heading::Right);
+    }
+
+    /// Moves the cursor a square in `heading` on the board as it's drawn, stopping at the edges.
+    fn move_cursor(&mut self, heading: Heading) {
+        self.heading = Some(heading);
+
+        let board = self.game.board();
+        let view = self.config.view(board);
+        let mut square = self.config.view_square(self.cursor, board);
+        match heading {
+            Heading::Up => square.y = square.y.saturating_sub(1),
+            Heading::Down => square.y = (square.y + 1).min(view.height() - 1),
+            Heading::Left => square.x = square.x.saturating_sub(1),
+            Heading::Right => square.x = (square.x + 1).min(view.width() - 1),
         }
+        // squares outside the play area are walls
+        let square = self.config.game_square(square, board);
+        if self.game.is_playable(square) {
+            self.cursor = square;
+        }
+    }
+
+    /// The name of `square` as labelled on the board as it's drawn.
+    fn name_of(&self, square: Square) -> String {
+        square_name(self.config.view_square(square, self.game.board()))
     }
 
     fn select(&mut self) {
+        let name = self.name_of(self.cursor);
+
         // try removing the square - if it wasn't in the region, we're trying to add it
-        if !self.uncommitted.remove_square(self.cursor) {
+        if self.remove_from_selection(self.cursor) {
+            self.announce(format!("deselected {name}"));
+        } else {
             // only add it if it's not currently occupied
             if self.game.is_square_free(self.cursor) {
-                self.uncommitted.add_square(self.cursor);
+                self.add_to_selection(self.cursor);
+
+                let c = self.game.board().letter(self.cursor);
+                self.announce(format!("selected {name} (letter {c})"));
+
+                if self.auto_advance {
+                    self.advance();
+                }
+            } else {
+                self.announce(format!("{name} is already taken"));
             }
         }
+
+        self.recheck_uncommitted();
     }
 
-    fn add(&mut self) {
-        // if the region is ok to add, add it
-        if let Ok(checked_region) = self.game.check_region(&self.uncommitted) {
-            // grab the next possible color, and refresh the list if we've run out
-            let color = self.colors.pop().unwrap();
-            if self.colors.is_empty() {
-                self.colors = self.all_colors.clone();
-            }
+    /// Moves the cursor one more step the way it last moved, as long as that's on to a square
+    /// which could be selected next.
+    fn advance(&mut self) {
+        let cursor = self.cursor;
+        match self.heading {
+            Some(Heading::Up) => self.cursor_up(),
+            Some(Heading::Down) => self.cursor_down(),
+            Some(Heading::Left) => self.cursor_left(),
+            Some(Heading::Right) => self.cursor_right(),
+            None => {}
+        }
 
-            // actually add the region and reset the uncommitted region
-            self.game.add_region(checked_region, color);
-            self.uncommitted = Region::new();
+        if !self.game.is_square_free(self.cursor) || self.uncommitted.contains(self.cursor) {
+            self.cursor = cursor;
         }
     }
 
-    fn remove(&mut self) {
-        // try removing the committed region under the cursor, but if there is none, reset the
-        // uncommitted region
-        if self.game.remove_region(self.cursor).is_none() {
-            self.uncommitted = Region::new();
-        }
+    /// Highlights the letter under the cursor, or stops highlighting if it already is.
+    fn toggle_highlight_letter(&mut self) {
+        let c = self.game.board().letter(self.cursor);
+        self.highlight_letter = match self.highlight_letter {
+            Some(highlighted) if highlighted == c => None,
+            _ => Some(c),
+        };
     }
 
-    fn remove_and_add(&mut self) {
-        // if the cursor is in a committed region, remove it and add every square from it to our
-        // uncommitted region
-        if let Some((region, _)) = self.game.remove_region(self.cursor) {
-            for square in region.squares() {
-                self.uncommitted.add_square(square);
-            }
+    fn toggle_auto_advance(&mut self) {
+        self.auto_advance = !self.auto_advance;
+        self.message = Some(if self.auto_advance {
+            "moving on after selecting".to_owned()
+        } else {
+            "staying put after selecting".to_owned()
+        });
+    }
+
+    fn deselect_last(&mut self) {
+        if let Some(&square) = self.selection.last() {
+            self.remove_from_selection(square);
+            self.recheck_uncommitted();
+            self.announce(format!("deselected {}", self.name_of(square)));
         }
     }
-}
 
-pub fn run(game: Game<Color>) -> io::Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    fn select_line(&mut self) {
+        // the first press drops an anchor, the second selects everything between it and the cursor
+        let anchor = match self.anchor.take() {
+            Some(anchor) => anchor,
+            None => {
+                self.anchor = Some(self.cursor);
+                self.message = Some(format!("anchored at {}", self.name_of(self.cursor)));
+                return;
+            }
+        };
 
-    let mut app = App::new(game);
-    while app.is_running() {
-        terminal.draw(|f| app.draw(f))?;
+        let (from, to) = (anchor.min(self.cursor), anchor.max(self.cursor));
+        let mut line = if from.x == to.x {
+            (from.y..=to.y)
+                .map(|y| (from.x, y).into())
+                .collect::<Vec<Square>>()
+        } else if from.y == to.y {
+            (from.x..=to.x).map(|x| (x, from.y).into()).collect()
+        } else {
+            self.message = Some("can only select a straight line".to_owned());
+            return;
+        };
+        // the line is selected starting from the anchor
+        if anchor != from {
+            line.reverse();
+        }
 
-        if event::poll(Duration::from_millis(100))? {
-            app.on_event(event::read()?);
+        if !line.iter().all(|&s| self.game.is_square_free(s)) {
+            self.message = Some("part of that line is already taken".to_owned());
+            return;
         }
-    }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        for square in line {
+            self.add_to_selection(square);
+        }
+        self.recheck_uncommitted();
 
-    Ok(())
+        self.announce(format!(
+            "selected {} to {}",
+            self.name_of(from),
+            self.name_of(to)
+        ));
+    }
+
+    fn add(&mut self) {
+        // if the region is ok to add, add it
+        let word = self.uncommitted_word();
+        match self.game.check_region_as(&self.uncommitted, &word) {
+            Ok(checked_region) => {
+                let shared = self.palette.is_exhausted();
+                let color = self.palette.next_color();
+
+                // remember where the region went so tab can jump back to it
+                self.last_committed = self.uncommitted.centroid_square();
+                self.trail = Some(Trail {
+                    region: self.uncommitted.clone(),
+                    started: Instant::now(),
+                });
+
+                // actually add the region and reset the uncommitted region
+                let placed_at = self.elapsed();
+                self.game
+                    .add_region(checked_region, RegionData { color, placed_at });
+                self.clear_selection();
+                self.game_changed();
+
+                self.announce(format!("committed {word}"));
+                if shared && self.config.palette_overflow == PaletteOverflow::Warn {
+                    self.message = Some("out of colors, so regions are sharing them".to_owned());
+                }
+
+                self.check_won();
+            }
+            // an empty selection isn't really an attempt, so don't count it as a mistake
+            Err(_) if self.uncommitted.size() == 0 => {}
+            Err(_) => self.reject(),
+        }
+    }
+
+    fn check_won(&mut self) {
+        // in a marathon, finishing a puzzle moves straight on to the next one, otherwise hold on to
+        // the finished board until a key is pressed
+        if self.is_won() {
+            if self.marathon.is_some() {
+                self.end(Ending::Completed);
+            } else {
+                self.done = true;
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        let regions = self.game.regions().count();
+        match self.game.undo() {
+            Some(changed) => self.history_changed("undo", regions, &changed),
+            None => self.message = Some("nothing to undo".to_owned()),
+        }
+    }
+
+    fn redo(&mut self) {
+        let regions = self.game.regions().count();
+        match self.game.redo() {
+            Some(changed) => self.history_changed("redo", regions, &changed),
+            None => self.message = Some("nothing to redo".to_owned()),
+        }
+    }
+
+    /// Catches up with an undone or redone change to the `changed` regions, when there were
+    /// `regions` regions before it.
+    fn history_changed(&mut self, action: &str, regions: usize, changed: &[(Region, RegionData)]) {
+        let change = match self.game.regions().count().cmp(&regions) {
+            Ordering::Greater => {
+                for (_, data) in changed {
+                    self.palette.use_color(data.color);
+                }
+                "put back"
+            }
+            Ordering::Less => {
+                for (_, data) in changed {
+                    self.palette.release_color(data.color);
+                }
+                "took away"
+            }
+            Ordering::Equal => "reshaped",
+        };
+
+        // a region coming back takes its squares out of the selection
+        for (region, _) in changed {
+            for square in region.squares() {
+                if !self.game.is_square_free(square) {
+                    self.remove_from_selection(square);
+                }
+            }
+        }
+        self.recheck_uncommitted();
+        self.game_changed();
+
+        let what = match changed {
+            [(region, _)] => region.word(self.game.board()),
+            _ => format!("{} regions", changed.len()),
+        };
+        let message = format!("{action}: {change} {what}");
+        self.announce(message.clone());
+        self.message = Some(message);
+        self.check_won();
+    }
+
+    fn reject(&mut self) {
+        let word = self.uncommitted_word();
+        let reason = describe_check(
+            &self.game,
+            &self.uncommitted,
+            &word,
+            &self.uncommitted_check,
+        );
+        let message = format!("rejected: {reason}");
+        self.announce(message.clone());
+        self.message = Some(message);
+
+        if let Some(lives) = &mut self.lives {
+            *lives = lives.saturating_sub(1);
+        }
+    }
+
+    fn remove(&mut self, pending_removal: Option<Region>) {
+        // try removing the committed region under the cursor, but if there is none, reset the
+        // uncommitted region
+        if !self.delete_region(pending_removal) {
+            self.clear_selection();
+            self.announce("cleared selection");
+        }
+    }
+
+    /// Removes the committed region under the cursor, if there is one, leaving the selection alone.
+    /// With --confirm-remove, a region worth enough points is only removed if it was the one asked
+    /// about by the last key press, `pending_removal`, and otherwise is asked about now.
+    fn delete_region(&mut self, pending_removal: Option<Region>) -> bool {
+        let region = self
+            .game
+            .regions()
+            .find_map(|(region, _)| region.contains(self.cursor).then_some(region));
+        let needs_confirming = match (region, self.config.confirm_remove) {
+            (Some(region), Some(threshold)) => {
+                self.game.score_region(region) >= threshold
+                    && pending_removal.as_ref() != Some(region)
+            }
+            _ => false,
+        };
+        if let (Some(region), true) = (region, needs_confirming) {
+            let word = region.word(self.game.board());
+            let score = self.game.score_region(region);
+            self.pending_removal = Some(region.clone());
+            // pick the region out from the rest while it's waiting
+            self.focused = Some(self.cursor);
+            self.message = Some(format!(
+                "remove {word} ({score} points)? press again to confirm"
+            ));
+            return true;
+        }
+
+        let (region, data) = match self.game.remove_region(self.cursor) {
+            Some(removed) => removed,
+            None => return false,
+        };
+        self.palette.release_color(data.color);
+
+        let word = region.word(self.game.board());
+        self.announce(format!("removed {word}"));
+        self.game_changed();
+
+        true
+    }
+
+    /// Removes every region and clears the selection, but only if the last key press,
+    /// `pending_clear`, already asked about it. Otherwise it's asked about now.
+    fn clear_board(&mut self, pending_clear: bool) {
+        let regions = self.game.regions().count();
+        if regions == 0 {
+            self.clear_selection();
+            self.announce("cleared selection");
+            return;
+        }
+        if !pending_clear {
+            self.pending_clear = true;
+            let plural = if regions == 1 { "region" } else { "regions" };
+            self.message = Some(format!(
+                "clear all {regions} {plural}? press R again to confirm"
+            ));
+            return;
+        }
+
+        for (_, data) in self.game.clear() {
+            self.palette.release_color(data.color);
+        }
+        self.clear_selection();
+        self.announce("cleared the board");
+        self.message = Some("cleared the board".to_owned());
+        self.game_changed();
+    }
+
+    fn remove_and_add(&mut self) {
+        // if the cursor is in a committed region, remove it and add every square from it to our
+        // uncommitted region
+        if let Some((region, data)) = self.game.remove_region(self.cursor) {
+            self.palette.release_color(data.color);
+
+            let word = region.word(self.game.board());
+            self.announce(format!("lifted {word}"));
+
+            for square in region.iter_sorted() {
+                self.add_to_selection(square);
+            }
+            self.recheck_uncommitted();
+
+            if let Some(centroid) = region.centroid_square() {
+                self.cursor = centroid;
+            }
+        }
+        self.game_changed();
+    }
+
+    fn cycle_regions(&mut self) {
+        // jump to the region we last committed if we haven't already, otherwise move on to the
+        // next region (in reading order) after the cursor
+        if let Some(square) = self.last_committed.take() {
+            if !self.game.is_square_free(square) {
+                self.cursor = square;
+                return;
+            }
+        }
+
+        let mut centroids = self
+            .game
+            .regions()
+            .filter_map(|(region, _)| region.centroid_square())
+            .collect::<Vec<_>>();
+        centroids.sort_unstable();
+
+        let next = centroids
+            .iter()
+            .find(|&&s| s > self.cursor)
+            .or_else(|| centroids.first());
+
+        if let Some(&next) = next {
+            self.cursor = next;
+        }
+    }
+}
+
+fn play<B: Backend + Write>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    let mut title = String::new();
+    while app.is_running() {
+        let new_title = app.title();
+        if new_title != title {
+            execute!(terminal.backend_mut(), SetTitle(&new_title))?;
+            title = new_title;
+        }
+
+        app.tick();
+        terminal.draw(|f| app.draw(f))?;
+
+        // redraw more often while something is moving
+        let timeout = if app.is_animating() {
+            TRAIL_STEP / 2
+        } else {
+            Duration::from_millis(100)
+        };
+        if event::poll(timeout)? {
+            app.on_event(event::read()?);
+        }
+        if app.take_bell() {
+            write!(terminal.backend_mut(), "\x07")?;
+            Write::flush(terminal.backend_mut())?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run<'a>(
+    game: Game<'a, RegionData>,
+    puzzle: Puzzle,
+    config: Config,
+    marathon: Option<Marathon>,
+) -> Result<Outcome<'a>, Error> {
+    let mut app = App::new(game, puzzle, config, marathon);
+
+    let (min_width, min_height) = app.min_size();
+    let (width, height) = crossterm::terminal::size()?;
+    log::info!("terminal is {width}x{height}, board needs {min_width}x{min_height}");
+    if width < min_width || height < min_height {
+        return Err(Error::TerminalSize {
+            width,
+            height,
+            min_width,
+            min_height,
+        });
+    }
+
+    let mut inline = app.config.inline;
+    let mut stdout = io::stdout();
+    // some terminals don't have an alternate screen, but drawing inline works anywhere
+    if !inline && execute!(stdout, EnterAlternateScreen).is_err() {
+        eprintln!("warning: couldn't switch to the alternate screen, drawing inline instead");
+        log::warn!("couldn't switch to the alternate screen");
+        inline = true;
+    }
+    if inline {
+        // scroll whatever's already on screen up into the scrollback, so we have the whole screen
+        // to ourselves without drawing over the prompt
+        write!(stdout, "{}", "\n".repeat(height.into()))?;
+    }
+    if let Err(e) = enable_raw_mode() {
+        // nothing's been drawn yet, so leaving the alternate screen is all there is to undo
+        if !inline {
+            let _ = execute!(stdout, LeaveAlternateScreen);
+        }
+        return Err(e.into());
+    }
+    // save the current title on xterm's title stack so we can put it back afterwards
+    write!(stdout, "\x1b[22;0t")?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    if inline {
+        terminal.clear()?;
+    }
+
+    log::info!("starting the game loop");
+    let result = play(&mut terminal, &mut app);
+    if let Err(e) = &result {
+        log::error!("game loop failed: {e}");
+    }
+
+    // put the terminal back the way we found it even if the game loop failed, going through every
+    // step even if one fails so as much as possible is put back
+    let mut restored = vec![
+        disable_raw_mode(),
+        write!(terminal.backend_mut(), "\x1b[23;0t"),
+    ];
+    if inline {
+        // leave the last frame where it is and start the prompt on a fresh line underneath it
+        let last_row = crossterm::terminal::size().map(|(_, height)| height.saturating_sub(1));
+        restored.push(terminal.show_cursor());
+        restored.push(last_row.and_then(|row| execute!(terminal.backend_mut(), MoveTo(0, row))));
+        restored.push(writeln!(terminal.backend_mut()));
+    } else {
+        restored.push(execute!(terminal.backend_mut(), LeaveAlternateScreen));
+    }
+    restored.into_iter().collect::<io::Result<()>>()?;
+    result?;
+
+    Ok(Outcome {
+        elapsed: app.elapsed(),
+        solved: app.is_won(),
+        game: app.game,
+        ending: app.ending,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+    use tui::backend::TestBackend;
+
+    fn board() -> Board {
+        #[rustfmt::skip]
+        let board = Board::new(
+            3,
+            concat!(
+                "ABC",
+                "DEF",
+                "GHI",
+            )
+        )
+        .unwrap();
+
+        board
+    }
+
+    fn ruleset() -> Ruleset {
+        let dictionary = ["ABC", "DEF", "GHI"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+
+        Ruleset::new(3, 3, dictionary)
+    }
+
+    fn puzzle() -> Puzzle {
+        Puzzle {
+            id: Some(1),
+            date: Some(NaiveDate::from_ymd(2022, 5, 6)),
+            title: None,
+            author: None,
+            words: 3,
+            // the rows, top to bottom
+            solution: (0..3)
+                .map(|y| Region::from_squares((0..3).map(|x| (x, y).into())))
+                .collect(),
+            median_time: None,
+            progress: None,
+        }
+    }
+
+    fn config() -> Config {
+        Config::from_iter(["paperbark"])
+    }
+
+    fn draw(app: &App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn press(app: &mut App, keys: &str) {
+        // a little script: letters are typed as they are, and a few symbols stand in for special keys
+        for c in keys.chars() {
+            let code = match c {
+                '>' => KeyCode::Right,
+                '<' => KeyCode::Left,
+                '^' => KeyCode::Up,
+                '_' => KeyCode::Down,
+                '.' => KeyCode::Enter,
+                '#' => KeyCode::Backspace,
+                '~' => KeyCode::Delete,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            app.on_event(Event::Key(code.into()));
+        }
+    }
+
+    #[test]
+    fn time_comparison() {
+        let compare = |elapsed, average| {
+            compare_time(Duration::from_secs(elapsed), Duration::from_secs(average))
+        };
+
+        assert_eq!(compare(80, 100), "20% faster than your average");
+        assert_eq!(compare(150, 100), "50% slower than your average");
+        assert_eq!(compare(100, 100), "same as your average");
+        assert_eq!(compare(10, 0), "same as your average");
+    }
+
+    #[test]
+    fn puzzle_describe() {
+        let board = board();
+        assert_eq!(
+            puzzle().describe(&board),
+            "puzzle 1 (2022-05-06) - 3x3, 3 words"
+        );
+
+        let custom = Puzzle {
+            id: None,
+            date: None,
+            ..puzzle()
+        };
+        assert_eq!(custom.describe(&board), "custom puzzle - 3x3, 3 words");
+    }
+
+    #[test]
+    fn scripted_solve() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        // select the top row square by square, and commit it
+        press(&mut app, " > > .");
+        assert_eq!(app.game.regions().count(), 1);
+        assert_eq!(app.uncommitted.size(), 0);
+        assert_eq!(app.cursor, (2, 0).into());
+
+        // the middle row with a line selection, going backwards
+        press(&mut app, "_v<<v.");
+        assert_eq!(app.game.regions().count(), 2);
+        assert!(!app.game.is_complete());
+
+        // change our minds about the middle row, then put everything in place
+        press(&mut app, "#");
+        assert_eq!(app.game.regions().count(), 1);
+        press(&mut app, "v>>v._v<<v.");
+        assert!(app.game.is_complete());
+        assert_eq!(app.game.score(), 27);
+        assert!(app.done);
+        assert!(app.is_running());
+
+        press(&mut app, "x");
+        assert!(!app.is_running());
+        assert!(matches!(app.ending, Ending::Completed));
+    }
+
+    #[test]
+    fn scripted_mistakes() {
+        let board = board();
+        let ruleset = ruleset();
+        let config = Config::from_iter(["paperbark", "--lives", "2"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+
+        // down the first column isn't a word
+        press(&mut app, "v__v.");
+        assert_eq!(app.game.regions().count(), 0);
+        assert_eq!(app.lives, Some(1));
+        assert_eq!(
+            app.message.as_deref(),
+            Some("rejected: unknown word \"ADG\"")
+        );
+
+        // a rejected selection sticks around, but committing nothing once it's cleared isn't a
+        // mistake
+        assert_eq!(app.uncommitted.size(), 3);
+        press(&mut app, "~.");
+        assert_eq!(app.lives, Some(1));
+
+        // but a second bad word ends the game, after which only quitting works
+        press(&mut app, "v>v.");
+        assert_eq!(app.lives, Some(0));
+        let cursor = app.cursor;
+        press(&mut app, "~v^v.");
+        assert_eq!(app.cursor, cursor);
+        assert_eq!(app.uncommitted.size(), 2);
+        press(&mut app, "q");
+        assert!(!app.is_running());
+        assert!(matches!(app.ending, Ending::Quit));
+    }
+
+    #[test]
+    fn selection_order() {
+        let board = board();
+        let ruleset = ruleset();
+        let selection_config = Config::from_iter(["paperbark", "--word-order", "selection"]);
+        let mut app = App::new(
+            Game::new(&board, &ruleset),
+            puzzle(),
+            selection_config,
+            None,
+        );
+
+        press(&mut app, " > > ");
+        assert_eq!(app.uncommitted_word(), "ABC");
+        assert_eq!(app.uncommitted_check, Ok(()));
+
+        // taking a square out and putting it back moves it to the end
+        press(&mut app, "< ");
+        assert_eq!(app.selection, [(0, 0).into(), (2, 0).into()]);
+        press(&mut app, " ");
+        assert_eq!(app.uncommitted_word(), "ACB");
+        assert_eq!(
+            app.uncommitted_check,
+            Err(vec![CheckRegionError::NotInDictionary])
+        );
+
+        // lines are read from the anchor
+        press(&mut app, "~>v<<v");
+        assert_eq!(app.uncommitted_word(), "CBA");
+        assert_eq!(
+            app.uncommitted_check,
+            Err(vec![CheckRegionError::NotInDictionary])
+        );
+
+        // but by default, the order doesn't matter
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, ">>v<<v");
+        assert_eq!(app.uncommitted_word(), "ABC");
+        assert_eq!(app.uncommitted_check, Ok(()));
+    }
+
+    #[test]
+    fn uncommitted_check_is_cached() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        for x in 0..3 {
+            app.cursor = (x, 0).into();
+            app.select();
+        }
+        assert_eq!(app.uncommitted_check, Ok(()));
+
+        // sneak a square into the region behind the app's back - redrawing shouldn't notice
+        app.uncommitted.add_square((1, 2).into());
+        draw(&app);
+        draw(&app);
+        assert_eq!(app.uncommitted_check, Ok(()));
+
+        // but changing the selection should
+        app.cursor = (0, 0).into();
+        app.select();
+        assert_eq!(
+            app.uncommitted_check,
+            Err(vec![
+                CheckRegionError::NotContiguous,
+                CheckRegionError::NotInDictionary
+            ])
+        );
+
+        app.select();
+        assert_eq!(
+            app.uncommitted_check,
+            Err(vec![
+                CheckRegionError::TooLong,
+                CheckRegionError::NotContiguous,
+                CheckRegionError::NotInDictionary
+            ])
+        );
+    }
+
+    #[test]
+    fn every_problem_described() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, " >> ");
+        assert_eq!(
+            describe_check(&app.game, &app.uncommitted, "AC", &app.uncommitted_check),
+            "word too short; region must be contiguous (2 pieces); unknown word \"AC\""
+        );
+
+        // pieces which meet across the edge of a wrapped board are only one piece
+        let config = Config::from_iter(["paperbark", "--wrap"]);
+        let mut ruleset = ruleset;
+        config.configure_ruleset(&mut ruleset);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+        press(&mut app, " >> _< ");
+        assert_eq!(
+            describe_check(&app.game, &app.uncommitted, "ACE", &app.uncommitted_check),
+            "region must be contiguous (2 pieces); unknown word \"ACE\""
+        );
+    }
+
+    #[test]
+    fn labels() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        app.on_event(Event::Key(KeyCode::Char('g').into()));
+        let lines = draw(&app);
+        assert_eq!(&lines[1][17..22], "  ABC");
+        assert_eq!(&lines[3][17..22], "1 ABC");
+        assert_eq!(&lines[5][17..22], "3 GHI");
+    }
+
+    #[test]
+    fn cell_width() {
+        let board = board();
+        let ruleset = ruleset();
+        let config = Config::from_iter(["paperbark", "--cell-width", "3"]);
+        let app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+
+        // 15 columns wide including the border, centered in 40
+        let lines = draw(&app);
+        assert_eq!(&lines[4][12..27], "    D  E  F    ");
+    }
+
+    #[test]
+    fn trail_is_skippable() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        app.cursor = (0, 0).into();
+        app.select_line();
+        app.cursor = (2, 0).into();
+        app.select_line();
+        app.add();
+        assert!(app.is_animating());
+
+        // the trail runs along the region a square at a time
+        let trail = app.trail.as_ref().unwrap();
+        let at_step = |step| trail.head_at(trail.started + TRAIL_STEP * step);
+        assert_eq!(at_step(0), Some((0, 0).into()));
+        assert_eq!(at_step(2), Some((2, 0).into()));
+        assert_eq!(at_step(3), None);
+
+        // the key still does what it normally would
+        app.on_event(Event::Key(KeyCode::Down.into()));
+        assert!(!app.is_animating());
+        assert_eq!(app.cursor, (2, 1).into());
+    }
+
+    #[test]
+    fn recommitting_updates_placed_at() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        app.cursor = (0, 0).into();
+        app.select_line();
+        app.cursor = (2, 0).into();
+        app.select_line();
+        app.add();
+        let (_, data) = app.game.regions().next().unwrap();
+        assert!(data.placed_at < Duration::from_secs(10));
+
+        // pretend the game has been going a while, then lift the region and put it back
+        app.started = app.started.checked_sub(Duration::from_secs(10)).unwrap();
+        app.remove_and_add();
+        app.add();
+        let (_, data) = app.game.regions().next().unwrap();
+        assert!(data.placed_at >= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn theme() {
+        let theme = Theme::new(&config());
+        assert_eq!(theme.committed_background, Some(Color::DarkGray));
+
+        let config = Config::from_iter(["paperbark", "--theme", "light"]);
+        assert_eq!(
+            Theme::new(&config).committed_background,
+            Some(Color::Indexed(254))
+        );
+
+        let config = Config::from_iter(["paperbark", "--region-background", "none"]);
+        assert_eq!(Theme::new(&config).committed_background, None);
+        let config = Config::from_iter(["paperbark", "--region-background", "17"]);
+        assert_eq!(
+            Theme::new(&config).committed_background,
+            Some(Color::Indexed(17))
+        );
+        assert!(Config::from_iter_safe(["paperbark", "--region-background", "mauve"]).is_err());
+
+        // both ends of the selection are the theme's shades, whichever way they run
+        assert_eq!(reading_order_color(0, 3, (255, 244)), Color::Indexed(255));
+        assert_eq!(reading_order_color(2, 3, (255, 244)), Color::Indexed(244));
+        assert_eq!(reading_order_color(2, 3, (232, 243)), Color::Indexed(243));
+    }
+
+    #[test]
+    fn dead_end_warning() {
+        let board = board();
+        let dictionary = ["ABC", "DEF", "GHI", "DEH"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, " > > .");
+        assert!(!app.dead_end);
+
+        // DEH leaves F on its own, which is a dead pocket and said as such
+        press(&mut app, "_<< > _ .");
+        assert!(app.dead_pocket);
+        assert!(!app.dead_end);
+        press(&mut app, "#");
+
+        // without DEF and GHI, DEH fits below ABC but can't be part of finishing the board
+        let dictionary = ["ABC", "DEH"].into_iter().map(|w| w.to_owned()).collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, " > > .");
+        assert!(!app.dead_pocket);
+        assert!(app.dead_end);
+        assert!(draw(&app)
+            .iter()
+            .any(|line| line.contains("[no valid completion remains]")));
+
+        // without looking, it's never known
+        let config = Config::from_iter(["paperbark", "--completion-budget", "0"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+        press(&mut app, " > > .");
+        assert!(!app.dead_end);
+    }
+
+    #[test]
+    fn dead_pocket_warning() {
+        let board = board();
+        let dictionary = ["ABC", "DEF", "GHI", "BEH"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, "v>>v.");
+        assert!(!app.dead_pocket);
+        press(&mut app, "#");
+
+        // the middle column leaves ADG and CFI on either side of it, neither of which is a word
+        press(&mut app, "<v__v.");
+        assert_eq!(app.game.regions().count(), 1);
+        assert!(app.dead_pocket);
+        assert!(draw(&app).iter().any(|line| line.contains("[dead pocket]")));
+
+        press(&mut app, "#");
+        assert!(!app.dead_pocket);
+    }
+
+    #[test]
+    fn recolor() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, "v>>v.");
+        let color = |app: &App| app.game.regions().next().unwrap().1.color;
+        assert_eq!(color(&app), Color::Red);
+
+        press(&mut app, "c");
+        assert_eq!(color(&app), Color::Green);
+        // the palette knows the new color is taken
+        press(&mut app, "_v<<v.");
+        assert_eq!(app.game.regions().nth(1).unwrap().1.color, Color::Yellow);
+
+        // nothing to recolor on a free square
+        press(&mut app, "_c");
+        assert_eq!(color(&app), Color::Green);
+    }
+
+    #[test]
+    fn edit_region() {
+        let board = board();
+        let dictionary = ["AB", "ABC", "DEF", "GHI"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(2, 3, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, "v>>v.");
+        let color = app.game.regions().next().unwrap().1.color;
+
+        // squares outside the region can't be picked up while editing
+        press(&mut app, "e_ ^ ");
+        assert!(draw(&app)
+            .iter()
+            .any(|line| line.contains("editing ABC into AB")));
+        press(&mut app, ".");
+        assert!(matches!(app.mode, Mode::Normal));
+        let (region, data) = app.game.regions().next().unwrap();
+        assert_eq!(region.word(&board), "AB");
+        assert_eq!(data.color, color);
+        assert_eq!(app.uncommitted.size(), 0);
+
+        // a single letter is too short, so the edit is kept open until it's cancelled
+        press(&mut app, "<e< .");
+        assert!(matches!(app.mode, Mode::Edit(_)));
+        assert_eq!(app.message.as_deref(), Some("can't edit: word too short"));
+        press(&mut app, "\x1b");
+        assert!(app.is_running());
+        assert!(matches!(app.mode, Mode::Normal));
+        assert_eq!(app.game.regions().next().unwrap().0.word(&board), "AB");
+    }
+
+    #[test]
+    fn remaining_letters() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, "v>>v._r");
+        assert!(draw(&app)
+            .iter()
+            .any(|line| line.contains("remaining: D E F G H I")));
+
+        // a selection's check takes priority
+        press(&mut app, " ");
+        assert!(!draw(&app).iter().any(|line| line.contains("remaining")));
+    }
+
+    #[test]
+    fn prefill() {
+        let board = board();
+        let ruleset = ruleset();
+        let prefill_config = Config::from_iter(["paperbark", "--prefill", "2"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), prefill_config, None);
+
+        // the first two rows are placed in colors of their own, leaving the last to do
+        let colors = app
+            .game
+            .regions()
+            .map(|(_, data)| data.color)
+            .collect::<Vec<_>>();
+        assert_eq!(colors.len(), 2);
+        assert_ne!(colors[0], colors[1]);
+        assert!(app.game.is_square_free((0, 2).into()));
+        assert!(!app.done);
+
+        press(&mut app, "__ > > .");
+        assert!(app.game.is_complete());
+        assert!(app.done);
+
+        // prefilling the whole thing leaves nothing to do
+        let prefill_config = Config::from_iter(["paperbark", "--prefill", "5"]);
+        let app = App::new(Game::new(&board, &ruleset), puzzle(), prefill_config, None);
+        assert!(app.done);
+    }
+
+    #[test]
+    fn cursor_start() {
+        let board = Board::new(4, "ABCDEFGH").unwrap();
+        assert_eq!(center_square(&board), (1, 0).into());
+        let board = Board::new(5, "ABCDEFGHIJKLMNO").unwrap();
+        assert_eq!(center_square(&board), (2, 1).into());
+        let board = Board::new(1, "A").unwrap();
+        assert_eq!(center_square(&board), (0, 0).into());
+
+        let board = self::board();
+        let ruleset = ruleset();
+        let start = |args: &[&str]| {
+            let config = Config::from_iter(chain(["paperbark"], args.iter().copied()));
+            App::new(Game::new(&board, &ruleset), puzzle(), config, None).cursor
+        };
+        assert_eq!(start(&[]), (0, 0).into());
+        assert_eq!(start(&["--cursor-start", "center"]), (1, 1).into());
+
+        // a start inside a placed region moves to the closest free square
+        assert_eq!(start(&["--prefill", "1"]), (0, 1).into());
+        let args = ["--cursor-start", "center", "--prefill", "2"];
+        assert_eq!(start(&args), (1, 2).into());
+        assert_eq!(start(&["--prefill", "3"]), (0, 0).into());
+
+        // the prefilled top two rows are never picked
+        for _ in 0..10 {
+            let square = start(&["--cursor-start", "random", "--prefill", "2"]);
+            assert_eq!(square.y, 2);
+        }
+        // the same seed picks the same square
+        for seed in ["1", "2", "3"] {
+            let args = ["--cursor-start", "random", "--seed", seed];
+            assert_eq!(start(&args), start(&args));
+        }
+    }
+
+    #[test]
+    fn longest_word() {
+        let board = board();
+        let dictionary = ["ABC", "ADG", "ABEH"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 4, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        press(&mut app, "m");
+        assert_eq!(
+            app.message.as_deref(),
+            Some("longest word left: ABEH (4 letters)")
+        );
+
+        // every word needs the A, so committing ABC leaves nothing
+        press(&mut app, " > > .");
+        assert!(matches!(app.longest, Some(None)));
+        press(&mut app, "m");
+        assert!(!app.show_longest);
+        press(&mut app, "m");
+        assert_eq!(app.message.as_deref(), Some("no words left to place"));
+    }
+
+    #[test]
+    fn auto_advance() {
+        let board = board();
+        let ruleset = ruleset();
+        let advance_config = Config::from_iter(["paperbark", "--auto-advance"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), advance_config, None);
+
+        // the cursor hasn't moved yet, so there's no direction to move on in
+        press(&mut app, " ");
+        assert_eq!(app.cursor, (0, 0).into());
+
+        press(&mut app, "> ");
+        assert_eq!(app.cursor, (2, 0).into());
+        // the edge of the board stops it
+        press(&mut app, " .");
+        assert_eq!(app.cursor, (2, 0).into());
+        assert!(!app.game.is_square_free((0, 0).into()));
+
+        // as do squares which are already taken
+        press(&mut app, "_<_^ ");
+        assert_eq!(app.cursor, (1, 1).into());
+
+        press(&mut app, "~t");
+        assert!(!app.auto_advance);
+        press(&mut app, "<> ");
+        assert_eq!(app.cursor, (1, 1).into());
+    }
+
+    #[test]
+    fn selection_length() {
+        let board = board();
+        // AB is a word, so being too short is the only thing wrong with it
+        let dictionary = ["ABC", "DEF", "GHI", "AB"]
+            .into_iter()
+            .map(|w| w.to_owned())
+            .collect();
+        let ruleset = Ruleset::new(3, 3, dictionary);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        let status = |app: &App| draw(app).last().unwrap().trim().to_owned();
+
+        press(&mut app, "k");
+        assert_eq!(status(&app), "");
+        press(&mut app, " > ");
+        assert_eq!(status(&app), "[2 letters, need 3] word too short");
+
+        press(&mut app, "k");
+        assert_eq!(status(&app), "word too short");
+    }
+
+    #[test]
+    fn resume() {
+        let board = board();
+        let ruleset = ruleset();
+        let row = |y| Region::from_squares((0..3).map(|x| (x, y).into()));
+        let progress = |rows: usize| Progress {
+            regions: (0..rows)
+                .map(|y| SavedRegion {
+                    region: row(y),
+                    placed_at: Duration::from_secs(10 * y as u64),
+                    color: (y == 0).then_some(Color::Magenta),
+                })
+                .collect(),
+            elapsed: Duration::from_secs(60),
+            dropped: 0,
+        };
+
+        let resumed = Puzzle {
+            progress: Some(progress(2)),
+            ..puzzle()
+        };
+        let app = App::new(Game::new(&board, &ruleset), resumed, config(), None);
+        let colors = app.game.regions().map(|(_, data)| data.color);
+        // the saved color is kept, and the palette knows not to hand it straight out again
+        assert_eq!(colors.collect::<Vec<_>>(), [Color::Magenta, Color::Red]);
+        assert!(!app.done);
+        assert!(app.elapsed() >= Duration::from_secs(60));
+
+        // a finished puzzle stays finished, and so does its clock
+        let resumed = Puzzle {
+            progress: Some(progress(3)),
+            ..puzzle()
+        };
+        let app = App::new(Game::new(&board, &ruleset), resumed, config(), None);
+        assert!(app.done);
+        assert!(app.elapsed() < Duration::from_secs(61));
+        let game = Game::<RegionData>::new(&board, &ruleset);
+        assert!(progress(3).covers(&game));
+        assert!(!progress(2).covers(&game));
+
+        // regions which no longer pass the rules are left out, saying so
+        let strict = Ruleset::new(
+            3,
+            3,
+            ["ABC", "GHI"].into_iter().map(|w| w.to_owned()).collect(),
+        );
+        let resumed = Puzzle {
+            progress: Some(progress(3)),
+            ..puzzle()
+        };
+        let app = App::new(Game::new(&board, &strict), resumed, config(), None);
+        assert_eq!(app.game.regions().count(), 2);
+        assert!(!app.done);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("left out 1 saved region which no longer fit")
+        );
+
+        // along with any which didn't fit on the board when the save was read
+        let resumed = Puzzle {
+            progress: Some(Progress {
+                dropped: 1,
+                ..progress(3)
+            }),
+            ..puzzle()
+        };
+        let app = App::new(Game::new(&board, &strict), resumed, config(), None);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("left out 2 saved regions which no longer fit")
+        );
+
+        // when words are read in the order they're selected, which wasn't saved, only the shapes
+        // have to fit
+        let selected = Ruleset::new(
+            3,
+            3,
+            ["ACB", "DEF", "IHG"]
+                .into_iter()
+                .map(|w| w.to_owned())
+                .collect(),
+        );
+        let resumed = Puzzle {
+            progress: Some(progress(3)),
+            ..puzzle()
+        };
+        let selection_config = Config::from_iter(["paperbark", "--word-order", "selection"]);
+        let app = App::new(
+            Game::new(&board, &selected),
+            resumed,
+            selection_config,
+            None,
+        );
+        assert_eq!(app.game.regions().count(), 3);
+        assert!(app.done);
+    }
+
+    #[test]
+    fn log_needs_room() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, "l");
+
+        let (board_width, _) = board_size(
+            &app.config.view(app.game.board()),
+            app.config.cell_width(),
+            app.show_labels,
+        );
+        let render = |width| {
+            let mut terminal = Terminal::new(TestBackend::new(width, 10)).unwrap();
+            terminal.draw(|f| app.draw(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .iter()
+                .map(|cell| cell.symbol.as_str())
+                .collect::<String>()
+        };
+
+        // only room for the board, so the log would squash it
+        let screen = render(board_width);
+        assert!(screen.starts_with("term"));
+        assert!(!screen.contains("log"));
+
+        let screen = render(board_width + LOG_WIDTH);
+        assert!(!screen.contains("terminal too small"));
+        assert!(screen.contains("log"));
+    }
+
+    #[test]
+    fn highlight_letter() {
+        let board = Board::new(3, "ABACADAEA").unwrap();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        let highlighted = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+            terminal.draw(|f| app.draw(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .iter()
+                .filter(|cell| cell.bg == app.theme.same_letter)
+                .map(|cell| cell.symbol.clone())
+                .collect::<String>()
+        };
+        assert_eq!(highlighted(&app), "");
+
+        press(&mut app, "h");
+        assert_eq!(highlighted(&app), "AAAAA");
+
+        // moving to another letter and pressing it again switches over
+        press(&mut app, ">h");
+        assert_eq!(highlighted(&app), "B");
+        press(&mut app, "h");
+        assert_eq!(app.highlight_letter, None);
+    }
+
+    #[test]
+    fn progress_bar() {
+        let board = board();
+        let ruleset = ruleset();
+        let progress_config = Config::from_iter(["paperbark", "--progress-bar"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), progress_config, None);
+        let bar = |app: &App| draw(app)[8].trim().to_owned();
+        assert_eq!(bar(&app), "░░░░░");
+
+        press(&mut app, " > > .");
+        assert_eq!(bar(&app), "█░░░░");
+
+        press(&mut app, "_ < < ._ > > .");
+        assert_eq!(bar(&app), "█████");
+
+        // minimalists don't get one at all
+        let app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        assert!(draw(&app).iter().all(|line| !line.contains('░')));
+    }
+
+    #[test]
+    fn deselect_last() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        // nothing to take back yet
+        press(&mut app, "z");
+        assert_eq!(app.uncommitted.size(), 0);
+
+        // the last square added goes, wherever the cursor has got to since
+        press(&mut app, " > _ ^^z");
+        assert_eq!(app.selection, [(0, 0).into(), (1, 0).into()]);
+        assert!(!app.uncommitted.contains((1, 1).into()));
+
+        press(&mut app, "zz");
+        assert_eq!(app.uncommitted.size(), 0);
+        assert!(app.selection.is_empty());
+        assert_eq!(app.log.back().unwrap(), "deselected A1");
+    }
+
+    #[test]
+    fn palette_overflow() {
+        // eight one letter words, two more than there are colors
+        let board = Board::new(8, "ABCDEFGH").unwrap();
+        let dictionary = "ABCDEFGH".chars().map(String::from).collect();
+        let ruleset = Ruleset::new(1, 1, dictionary);
+        let fill = |overflow: &str| {
+            let config = Config::from_iter(["paperbark", "--palette-overflow", overflow]);
+            let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+            press(&mut app, &format!("{} .", " .>".repeat(7)));
+            let colors = app.game.regions().map(|(_, data)| data.color);
+            (colors.collect::<Vec<_>>(), app.message)
+        };
+
+        let (colors, message) = fill("least-used");
+        assert_eq!(colors[..2], colors[6..]);
+        assert_eq!(message, None);
+
+        let (colors, _) = fill("extend");
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[..i].contains(color));
+        }
+
+        let (colors, message) = fill("warn");
+        assert_eq!(colors[..2], colors[6..]);
+        assert_eq!(
+            message.as_deref(),
+            Some("out of colors, so regions are sharing them")
+        );
+    }
+
+    #[test]
+    fn play_area() {
+        assert_eq!(
+            "2x3".parse(),
+            Ok(PlayArea {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 3
+            })
+        );
+        assert_eq!(
+            "1x1+2+0".parse(),
+            Ok(PlayArea {
+                x: 2,
+                y: 0,
+                width: 1,
+                height: 1
+            })
+        );
+        for invalid in ["", "2", "2x", "0x1", "2x2+1", "2x2+1+1+1", "axb"] {
+            assert!(invalid.parse::<PlayArea>().is_err());
+        }
+
+        let board = board();
+        let ruleset = ruleset();
+        let config = |area: &str| Config::from_iter(["paperbark", "--play-area", area]);
+        assert!(config("3x1+0+2").new_game(&board, &ruleset).is_ok());
+        assert!(config("3x1+0+3").new_game(&board, &ruleset).is_err());
+        assert!(config("4x1").new_game(&board, &ruleset).is_err());
+
+        // the cursor can't leave the area, so nothing outside it can be selected
+        let config = config("2x2");
+        let game = config.new_game(&board, &ruleset).unwrap();
+        let mut app = App::new(game, puzzle(), config, None);
+        press(&mut app, ">>__ ");
+        assert_eq!(app.cursor, (1, 1).into());
+        assert_eq!(app.selection, [(1, 1).into()]);
+
+        // a finished game only has to cover the area, and the cursor starts out inside it
+        let config = Config::from_iter(["paperbark", "--play-area", "3x1+0+2"]);
+        let game = config.new_game(&board, &ruleset).unwrap();
+        let progress = Progress {
+            regions: vec![SavedRegion {
+                region: Region::from_squares((0..3).map(|x| (x, 2).into())),
+                placed_at: Duration::from_secs(10),
+                color: None,
+            }],
+            elapsed: Duration::from_secs(10),
+            dropped: 0,
+        };
+        assert!(progress.covers(&game));
+        let resumed = Puzzle {
+            progress: Some(progress),
+            ..puzzle()
+        };
+        let app = App::new(game, resumed, config, None);
+        assert!(app.done);
+        assert_eq!(app.cursor, (0, 2).into());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        let control = |app: &mut App, c| {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+            app.on_event(Event::Key(key));
+        };
+
+        control(&mut app, 'z');
+        assert_eq!(app.message.as_deref(), Some("nothing to undo"));
+
+        press(&mut app, " > > .#");
+        assert_eq!(app.game.regions().count(), 0);
+        control(&mut app, 'z');
+        assert_eq!(app.game.regions().count(), 1);
+        assert_eq!(app.message.as_deref(), Some("undo: put back ABC"));
+
+        // the region coming back takes its squares from the selection
+        press(&mut app, "_ ^ ");
+        assert_eq!(app.selection, [(2, 1).into()]);
+        control(&mut app, 'z');
+        control(&mut app, 'y');
+        assert_eq!(app.message.as_deref(), Some("redo: put back ABC"));
+        assert_eq!(app.selection, [(2, 1).into()]);
+
+        // plain z still takes back the last square selected
+        press(&mut app, "z");
+        assert!(app.selection.is_empty());
+        assert_eq!(app.game.regions().count(), 1);
+    }
+
+    #[test]
+    fn hovered_region() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        let bold_letters = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+            terminal.draw(|f| app.draw(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            // just the board's row, not the status bar
+            let cells = buffer.content.iter().take(buffer.content.len() - 40);
+            cells
+                .filter(|cell| cell.modifier.contains(Modifier::BOLD))
+                .map(|cell| cell.symbol.clone())
+                .collect::<String>()
+        };
+
+        // moving on clears the trail drawn over the newest region
+        press(&mut app, " > > ._ < < .<");
+        assert_eq!(bold_letters(&app), "DEF");
+        press(&mut app, "^");
+        assert_eq!(bold_letters(&app), "ABC");
+        press(&mut app, "__");
+        assert_eq!(bold_letters(&app), "");
+    }
+
+    #[test]
+    fn bell() {
+        let board = board();
+        let ruleset = ruleset();
+        let bell = Config::from_iter(["paperbark", "--bell"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), bell, None);
+
+        press(&mut app, " > > ._ < < .");
+        assert!(!app.take_bell());
+        press(&mut app, "_ > > .");
+        assert!(app.game.is_complete());
+        assert!(app.take_bell());
+        assert!(!app.take_bell());
+
+        // off unless asked for
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, " > > ._ < < ._ > > .");
+        assert!(app.game.is_complete());
+        assert!(!app.take_bell());
+    }
+
+    #[test]
+    fn rotate_and_mirror() {
+        let board = board();
+        let ruleset = ruleset();
+        let config = Config::from_iter(["paperbark", "--rotate", "1"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+
+        let lines = draw(&app);
+        for row in ["GDA", "HEB", "IFC"] {
+            assert!(lines.iter().any(|line| line.contains(row)));
+        }
+
+        // the cursor starts on A, now in the top right corner, and moves the way it's drawn
+        press(&mut app, ">");
+        assert_eq!(app.cursor, (0, 0).into());
+        press(&mut app, "<_");
+        assert_eq!(app.cursor, (1, 1).into());
+        assert_eq!(app.log.back().unwrap(), "cursor moved to B2");
+
+        let config = Config::from_iter(["paperbark", "--mirror", "vertical", "--rotate", "2"]);
+        let app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+        let lines = draw(&app);
+        for row in ["GHI", "DEF", "ABC"] {
+            assert!(lines.iter().any(|line| line.contains(row)));
+        }
+    }
+
+    #[test]
+    fn search() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        // none of these are commands while typing
+        press(&mut app, "/qwe#");
+        assert!(app.is_running());
+        assert!(matches!(&app.mode, Mode::Search(word) if word == "QW"));
+        assert_eq!(app.cursor, (0, 0).into());
+
+        press(&mut app, "##def.");
+        assert!(matches!(app.mode, Mode::Normal));
+        assert_eq!(
+            app.found,
+            [Region::from_squares([
+                (0, 1).into(),
+                (1, 1).into(),
+                (2, 1).into()
+            ])]
+        );
+        assert_eq!(app.message.as_deref(), Some("DEF fits in 1 place"));
+
+        // esc only leaves the search
+        press(&mut app, "/abd\x1b");
+        assert!(app.is_running());
+        assert!(app.found.is_empty());
+        press(&mut app, "q");
+        assert!(!app.is_running());
+    }
+
+    #[test]
+    fn focus() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        // nothing to focus on
+        app.on_event(Event::Key(KeyCode::Char('o').into()));
+        assert_eq!(app.focused, None);
+
+        app.select_line();
+        app.cursor = (2, 0).into();
+        app.select_line();
+        app.add();
+        app.on_event(Event::Key(KeyCode::Char('o').into()));
+        assert_eq!(app.focused, Some((2, 0).into()));
+        draw(&app);
+
+        app.on_event(Event::Key(KeyCode::Down.into()));
+        assert_eq!(app.focused, None);
+    }
+
+    #[test]
+    fn select_line() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        app.cursor = (2, 1).into();
+        app.select_line();
+        app.cursor = (0, 1).into();
+        app.select_line();
+        assert_eq!(app.uncommitted.word(&board), "DEF");
+        assert_eq!(app.uncommitted_check, Ok(()));
+
+        app.select_line();
+        app.cursor = (1, 2).into();
+        app.select_line();
+        assert_eq!(app.uncommitted.word(&board), "DEF");
+        assert_eq!(app.anchor, None);
+    }
+
+    #[test]
+    fn backspace_only_deletes_regions() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        app.cursor = (0, 0).into();
+        app.select_line();
+        app.cursor = (2, 0).into();
+        app.select_line();
+        app.add();
+        app.cursor = (1, 1).into();
+        app.select();
+
+        // nothing under the cursor, so the selection survives
+        app.on_event(Event::Key(KeyCode::Backspace.into()));
+        assert_eq!(app.uncommitted.size(), 1);
+
+        app.cursor = (1, 0).into();
+        app.on_event(Event::Key(KeyCode::Backspace.into()));
+        assert_eq!(app.game.regions().count(), 0);
+        assert_eq!(app.uncommitted.size(), 1);
+
+        // whereas delete clears it
+        app.on_event(Event::Key(KeyCode::Delete.into()));
+        assert_eq!(app.uncommitted.size(), 0);
+    }
+
+    #[test]
+    fn confirm_remove() {
+        let board = board();
+        let ruleset = ruleset();
+        let config = Config::from_iter(["paperbark", "--confirm-remove", "9"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+        press(&mut app, " > > .");
+
+        // the first press only asks
+        press(&mut app, "#");
+        assert_eq!(app.game.regions().count(), 1);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("remove ABC (9 points)? press again to confirm")
+        );
+        assert_eq!(app.focused, Some((2, 0).into()));
+
+        // anything else in between means asking again
+        press(&mut app, "<#>~");
+        assert_eq!(app.game.regions().count(), 1);
+        press(&mut app, "~");
+        assert_eq!(app.game.regions().count(), 0);
+
+        // words worth less than the threshold go straight away
+        let config = Config::from_iter(["paperbark", "--confirm-remove", "10"]);
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config, None);
+        press(&mut app, " > > .#");
+        assert_eq!(app.game.regions().count(), 0);
+    }
+
+    #[test]
+    fn hint() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, "__ i");
+        assert_eq!(
+            app.uncommitted,
+            Region::from_squares([(0, 0).into(), (1, 0).into(), (2, 0).into()])
+        );
+        assert_eq!(
+            app.message.as_deref(),
+            Some("ABC fits here, press enter to commit it")
+        );
+
+        press(&mut app, ".i");
+        assert_eq!(app.game.regions().count(), 1);
+        assert_eq!(app.selection, [(0, 1).into(), (1, 1).into(), (2, 1).into()]);
+
+        let board = Board::new(3, "XXXXXXXXX").unwrap();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, "i");
+        assert_eq!(app.message.as_deref(), Some("no words fit anywhere"));
+    }
+
+    #[test]
+    fn clear_board() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+        press(&mut app, " > > ._ < < ._ ");
+        assert_eq!(app.selection.len(), 1);
+
+        // the first press only asks, and anything else in between means asking again
+        press(&mut app, "R");
+        assert_eq!(app.game.regions().count(), 2);
+        assert_eq!(
+            app.message.as_deref(),
+            Some("clear all 2 regions? press R again to confirm")
+        );
+        press(&mut app, "<R");
+        assert_eq!(app.game.regions().count(), 2);
+
+        press(&mut app, "R");
+        assert_eq!(app.game.regions().count(), 0);
+        assert!(app.selection.is_empty());
+        assert_eq!(app.message.as_deref(), Some("cleared the board"));
+        assert_eq!(app.palette.counts.iter().sum::<usize>(), 0);
+
+        // and it can be undone, all at once
+        let key = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        app.on_event(Event::Key(key));
+        assert_eq!(app.game.regions().count(), 2);
+        assert_eq!(app.message.as_deref(), Some("undo: put back 2 regions"));
+        // with both colors back in use
+        assert_eq!(app.palette.counts.iter().sum::<usize>(), 2);
+        assert!(app.selection.is_empty());
+    }
+
+    #[test]
+    fn completed_board_waits_for_key() {
+        let board = board();
+        let ruleset = ruleset();
+        let mut app = App::new(Game::new(&board, &ruleset), puzzle(), config(), None);
+
+        for y in 0..3 {
+            app.cursor = (0, y).into();
+            app.select_line();
+            app.cursor = (2, y).into();
+            app.select_line();
+            app.add();
+        }
+        assert!(app.done);
+        draw(&app);
+
+        app.on_event(Event::Key(KeyCode::Left.into()));
+        assert!(app.is_running());
+
+        app.on_event(Event::Key(KeyCode::Enter.into()));
+        assert!(!app.is_running());
+        assert!(matches!(app.ending, Ending::Completed));
+    }
 }